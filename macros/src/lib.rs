@@ -0,0 +1,84 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Error, FnArg, ItemFn};
+
+/// The most parameters a system can take, matching the `System` impls.
+const MAX_PARAMS: usize = 6;
+
+/// Marks a function as a system and generates `<name>_access()`, which
+/// returns the `SystemAccess` derived from its parameter types:
+///
+/// ```ignore
+/// #[system]
+/// fn physics(collides: Vec<&mut Collide>, time: Res<Time>) { ... }
+///
+/// assert!(physics_access().conflicts_with(&render_access()));
+/// ```
+///
+/// Signatures that can't be systems (methods, generics, too many
+/// parameters) are rejected here with an error pointing at the culprit,
+/// instead of a trait error at the `add_system` call.
+#[proc_macro_attribute]
+pub fn system(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(item as ItemFn);
+    if !attr.is_empty() {
+        let attr = proc_macro2::TokenStream::from(attr);
+        return Error::new(attr.span(), "#[system] takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    if let Err(error) = check_signature(&function) {
+        return error.to_compile_error().into();
+    }
+
+    let vis = &function.vis;
+    let name = &function.sig.ident;
+    let access_name = format_ident!("{}_access", name);
+    let doc = format!("What `{name}` reads and writes, derived from its parameter types.");
+    quote! {
+        #function
+
+        #[doc = #doc]
+        #vis fn #access_name() -> ::test_rust::SystemAccess {
+            ::test_rust::SystemAccess::of(&#name)
+        }
+    }
+    .into()
+}
+
+fn check_signature(function: &ItemFn) -> Result<(), Error> {
+    let signature = &function.sig;
+    if !signature.generics.params.is_empty() {
+        return Err(Error::new(
+            signature.generics.span(),
+            "systems can't be generic",
+        ));
+    }
+    if let Some(receiver) = signature.receiver() {
+        return Err(Error::new(receiver.span(), "systems can't take `self`"));
+    }
+    if signature.inputs.is_empty() {
+        return Err(Error::new(
+            signature.span(),
+            "systems take at least one parameter",
+        ));
+    }
+    if let Some(extra) = signature.inputs.iter().nth(MAX_PARAMS) {
+        return Err(Error::new(
+            extra.span(),
+            format!("systems take at most {MAX_PARAMS} parameters"),
+        ));
+    }
+    for input in &signature.inputs {
+        if let FnArg::Typed(argument) = input {
+            if let syn::Type::ImplTrait(_) = *argument.ty {
+                return Err(Error::new(
+                    argument.ty.span(),
+                    "system parameters must be concrete types",
+                ));
+            }
+        }
+    }
+    Ok(())
+}
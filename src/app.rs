@@ -0,0 +1,699 @@
+use crate::profile::SystemProfile;
+use crate::resource::ResMut;
+use crate::system::{QueryWarnings, StartupSystem, System, SystemAccess};
+use crate::time::Time;
+use crate::world::World;
+use std::any::type_name;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Set to `ShouldExit(true)` from a system to end `App::run_loop` after the
+/// current frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ShouldExit(pub bool);
+
+type BoxedSystem = Box<dyn Fn(&World)>;
+type BoxedStartupSystem = Box<dyn FnOnce(&mut World)>;
+type RunCondition = Box<dyn Fn(&World) -> bool>;
+
+/// Identifies one added system, for `App::remove_system`; get it from
+/// `SystemConfig::id`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SystemId(u64);
+
+struct SystemEntry {
+    id: SystemId,
+    label: &'static str,
+    set: Option<&'static str>,
+    before: Vec<&'static str>,
+    after: Vec<&'static str>,
+    conditions: Vec<RunCondition>,
+    access: SystemAccess,
+    system: BoxedSystem,
+    last_run_tick: Cell<u64>,
+}
+
+/// Systems live in named schedules. Each frame of `run_once` runs
+/// `FIXED_UPDATE` once per elapsed fixed step (see `set_fixed_rate`), then
+/// `UPDATE`, then `RENDER`; `run_schedule` runs any schedule on its own.
+pub struct App {
+    startup_systems: Vec<BoxedStartupSystem>,
+    schedules: HashMap<&'static str, Vec<SystemEntry>>,
+    next_system_id: u64,
+    disabled_sets: HashSet<&'static str>,
+    last_run: Option<Instant>,
+    fixed_delta: Option<Duration>,
+    fixed_step: Duration,
+    accumulator: Duration,
+    max_fixed_steps: u32,
+    profiling: bool,
+}
+
+impl App {
+    /// Where `add_system` puts systems; runs once per frame.
+    pub const UPDATE: &'static str = "Update";
+    /// Runs zero or more times per frame, once per fixed step, before
+    /// `UPDATE`.
+    pub const FIXED_UPDATE: &'static str = "FixedUpdate";
+    /// Runs once per frame, after `UPDATE`.
+    pub const RENDER: &'static str = "Render";
+
+    pub fn new() -> Self {
+        Self {
+            startup_systems: Vec::new(),
+            schedules: HashMap::new(),
+            next_system_id: 0,
+            disabled_sets: HashSet::new(),
+            last_run: None,
+            fixed_delta: None,
+            fixed_step: Duration::from_secs_f64(1.0 / 60.0),
+            accumulator: Duration::ZERO,
+            max_fixed_steps: 5,
+            profiling: false,
+        }
+    }
+    /// Runs `system` once, in registration order with the other startup
+    /// systems, before the first frame of whichever `run_*` is called
+    /// first. Commands they queue are applied right after. Panics, like
+    /// `add_system`, if the system's parameters conflict.
+    pub fn add_startup_system<Params, S>(&mut self, system: S)
+    where
+        S: StartupSystem<Params> + 'static,
+    {
+        S::access();
+        self.startup_systems
+            .push(Box::new(move |world: &mut World| system.run_startup(world)));
+    }
+    /// Adds `system` to the `UPDATE` schedule. Closures work as well as fns,
+    /// including `move` closures owning configuration like a difficulty
+    /// multiplier; the parameters' types must be spelled out. Systems are
+    /// labelled with their type name until `.label()` is called. Panics if
+    /// the system's parameters conflict (see `SystemAccess`), so aliasing
+    /// parameters are caught before the first frame. `.id()` on the result
+    /// gives the handle to `remove_system` it with later.
+    pub fn add_system<Params, S>(&mut self, system: S) -> SystemConfig<'_>
+    where
+        S: System<Params> + 'static,
+    {
+        self.add_system_to_schedule(Self::UPDATE, system)
+    }
+    /// Like `add_system`, into the named schedule, creating it if needed.
+    /// `before`/`after` only order systems within the same schedule.
+    pub fn add_system_to_schedule<Params, S>(
+        &mut self,
+        schedule: &'static str,
+        system: S,
+    ) -> SystemConfig<'_>
+    where
+        S: System<Params> + 'static,
+    {
+        let access = S::access();
+        let state = S::State::default();
+        let wrapped_system_func = Box::new(move |world: &World| {
+            system.run(&state, world);
+        });
+        let id = SystemId(self.next_system_id);
+        self.next_system_id += 1;
+        let systems = self.schedules.entry(schedule).or_default();
+        systems.push(SystemEntry {
+            id,
+            label: type_name::<S>(),
+            set: None,
+            before: Vec::new(),
+            after: Vec::new(),
+            conditions: Vec::new(),
+            access,
+            system: wrapped_system_func,
+            last_run_tick: Cell::new(0),
+        });
+        SystemConfig {
+            entry: systems.last_mut().unwrap(),
+        }
+    }
+    pub fn add_system_after<Params, S>(
+        &mut self,
+        label: &'static str,
+        system: S,
+    ) -> SystemConfig<'_>
+    where
+        S: System<Params> + 'static,
+    {
+        self.add_system(system).after(label)
+    }
+    pub fn add_system_before<Params, S>(
+        &mut self,
+        label: &'static str,
+        system: S,
+    ) -> SystemConfig<'_>
+    where
+        S: System<Params> + 'static,
+    {
+        self.add_system(system).before(label)
+    }
+    /// Adds a tuple of systems as the set `set`, which `set_enabled` can
+    /// switch on and off as a whole.
+    pub fn add_system_set<Params, S>(&mut self, set: &'static str, systems: S)
+    where
+        S: SystemSet<Params>,
+    {
+        systems.add_to_set(self, set);
+    }
+    /// Skips (or resumes running) every system in `set`, e.g. all debug
+    /// systems behind a runtime toggle. Sets are enabled by default.
+    pub fn set_enabled(&mut self, set: &'static str, enabled: bool) {
+        if enabled {
+            self.disabled_sets.remove(set);
+        } else {
+            self.disabled_sets.insert(set);
+        }
+    }
+    /// Lets `plugin` add its systems, and startup systems that set up the
+    /// world for them. Startup systems run in the order they were added, so
+    /// a plugin's setup can rely on that of plugins added before it.
+    pub fn add_plugin(&mut self, plugin: impl Plugin) {
+        plugin.build(self);
+    }
+    /// Makes every frame advance `Time` by `delta` instead of the measured
+    /// wall-clock time, for deterministic tests.
+    pub fn set_fixed_delta(&mut self, delta: Option<Duration>) {
+        self.fixed_delta = delta;
+    }
+    /// Runs one frame: the `FIXED_UPDATE` steps the elapsed time covers,
+    /// then `UPDATE` and `RENDER` once each with `Time` advanced by the
+    /// frame's delta. The first frame inserts `QueryWarnings` unless the
+    /// world has it already, so query errors are collected by default.
+    pub fn run_once(&mut self, world: &mut World) {
+        if self.last_run.is_none() && !world.contains_resource::<QueryWarnings>() {
+            world.insert_resource(QueryWarnings::default());
+        }
+        self.run_startup_systems(world);
+        let delta = self.measure_delta();
+        let alpha = self.run_fixed_steps(world, delta);
+        Self::advance_time(world, delta, alpha);
+        self.run_systems(Self::UPDATE, world, false);
+        self.run_systems(Self::RENDER, world, true);
+    }
+    /// Runs one schedule's systems once, then applies the commands they
+    /// queued. Doesn't touch `Time` or run startup systems; an unknown
+    /// schedule does nothing.
+    pub fn run_schedule(&self, schedule: &str, world: &mut World) {
+        self.run_systems(schedule, world, false);
+    }
+    /// Runs frames until a system sets the `ShouldExit` resource to true.
+    pub fn run_loop(&mut self, world: &mut World) {
+        while !world
+            .get_resource::<ShouldExit>()
+            .is_some_and(|should_exit| should_exit.0)
+        {
+            self.run_once(world);
+        }
+    }
+    /// Runs one frame like `run_once`, with `FIXED_UPDATE` stepping at `hz`.
+    pub fn run_fixed(&mut self, world: &mut World, hz: f64) {
+        self.set_fixed_rate(hz);
+        self.run_once(world);
+    }
+    /// How many times per second of elapsed time `FIXED_UPDATE` runs; 60
+    /// by default.
+    pub fn set_fixed_rate(&mut self, hz: f64) {
+        self.fixed_step = Duration::from_secs_f64(1.0 / hz);
+    }
+    /// Runs `FIXED_UPDATE` as many steps as the time elapsed since the last
+    /// frame covers, at most `max_fixed_steps` times; any backlog beyond
+    /// that is dropped so a stalled frame can't snowball. Returns the
+    /// leftover fraction of a step, exposed as `Time::alpha`.
+    fn run_fixed_steps(&mut self, world: &mut World, delta: Duration) -> f32 {
+        if !self.schedules.contains_key(Self::FIXED_UPDATE) {
+            return 0.0;
+        }
+        let step = self.fixed_step;
+        self.accumulator += delta;
+        let mut steps = 0;
+        while self.accumulator >= step {
+            if steps == self.max_fixed_steps {
+                self.accumulator =
+                    Duration::from_secs_f64(self.accumulator.as_secs_f64() % step.as_secs_f64());
+                break;
+            }
+            self.accumulator -= step;
+            Self::time_mut(world).set_delta(step);
+            self.run_systems(Self::FIXED_UPDATE, world, false);
+            steps += 1;
+        }
+        self.accumulator.as_secs_f32() / step.as_secs_f32()
+    }
+    pub fn set_max_fixed_steps(&mut self, max_fixed_steps: u32) {
+        self.max_fixed_steps = max_fixed_steps;
+    }
+    /// Records how long each system takes into the `SystemProfile`
+    /// resource, inserting it if needed.
+    pub fn enable_profiling(&mut self, enabled: bool) {
+        self.profiling = enabled;
+    }
+    /// Takes the system out of whichever schedule it was added to, e.g. when
+    /// a mod is unloaded; it doesn't run from the next frame on. Constraints
+    /// other systems have on its label are simply dropped. Returns false if
+    /// it was removed already.
+    pub fn remove_system(&mut self, id: SystemId) -> bool {
+        for systems in self.schedules.values_mut() {
+            if let Some(index) = systems.iter().position(|entry| entry.id == id) {
+                systems.remove(index);
+                return true;
+            }
+        }
+        false
+    }
+    /// The access of the system labelled `label`, in whichever schedule it
+    /// was added to.
+    pub fn system_access(&self, label: &str) -> Option<&SystemAccess> {
+        self.schedules
+            .values()
+            .flatten()
+            .find(|entry| entry.label == label)
+            .map(|entry| &entry.access)
+    }
+    /// Every pair of systems in the same schedule whose accesses conflict,
+    /// with the types they conflict on; see `SystemAccess::conflicts_with`.
+    /// Schedules are listed by name, systems in registration order.
+    pub fn conflict_report(&self) -> Vec<SystemConflict> {
+        let mut schedules: Vec<_> = self.schedules.iter().collect();
+        schedules.sort_by_key(|&(name, _)| *name);
+        let mut conflicts = Vec::new();
+        for (&schedule, systems) in schedules {
+            for (index, first) in systems.iter().enumerate() {
+                for second in &systems[index + 1..] {
+                    let types = first.access.conflicting_types(&second.access);
+                    if !types.is_empty() {
+                        conflicts.push(SystemConflict {
+                            schedule,
+                            first: first.label,
+                            second: second.label,
+                            types,
+                        });
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+    fn run_startup_systems(&mut self, world: &mut World) {
+        if self.startup_systems.is_empty() {
+            return;
+        }
+        for system in std::mem::take(&mut self.startup_systems) {
+            system(world);
+        }
+        world.apply_commands();
+    }
+    /// Runs a schedule's systems, then applies their commands. At the end of
+    /// a frame, temporaries are despawned before that.
+    fn run_systems(&self, schedule: &str, world: &mut World, end_of_frame: bool) {
+        if self.profiling && !world.contains_resource::<SystemProfile>() {
+            world.insert_resource(SystemProfile::default());
+        }
+        let systems = self.schedules.get(schedule).map_or(&[][..], Vec::as_slice);
+        for index in Self::schedule(systems) {
+            let entry = &systems[index];
+            if entry
+                .set
+                .is_some_and(|set| self.disabled_sets.contains(set))
+            {
+                continue;
+            }
+            if !entry.conditions.iter().all(|condition| condition(world)) {
+                continue;
+            }
+            world.set_last_run_tick(Some(entry.last_run_tick.get()));
+            if self.profiling {
+                let start = Instant::now();
+                (entry.system)(world);
+                let duration = start.elapsed();
+                world
+                    .resource_mut::<SystemProfile>()
+                    .record(entry.label, duration);
+            } else {
+                (entry.system)(world);
+            }
+            entry.last_run_tick.set(world.change_tick());
+        }
+        world.set_last_run_tick(None);
+        if end_of_frame {
+            world.despawn_temporaries();
+        }
+        // Commands count as changes of the next frame, so every system
+        // still gets to see them.
+        world.advance_tick();
+        world.apply_commands();
+    }
+    fn measure_delta(&mut self) -> Duration {
+        let now = Instant::now();
+        let delta = match (self.fixed_delta, self.last_run) {
+            (Some(delta), _) => delta,
+            (None, Some(last_run)) => now - last_run,
+            (None, None) => Duration::ZERO,
+        };
+        self.last_run = Some(now);
+        delta
+    }
+    fn advance_time(world: &mut World, delta: Duration, alpha: f32) {
+        let mut time = Self::time_mut(world);
+        time.advance(delta);
+        time.set_alpha(alpha);
+    }
+    fn time_mut(world: &mut World) -> ResMut<'_, Time> {
+        if !world.contains_resource::<Time>() {
+            world.insert_resource(Time::default());
+        }
+        world.resource_mut::<Time>()
+    }
+    /// Orders systems so every `before`/`after` constraint holds, otherwise
+    /// keeping registration order. Panics if the constraints form a cycle.
+    fn schedule(systems: &[SystemEntry]) -> Vec<usize> {
+        let count = systems.len();
+        let mut dependencies = vec![Vec::new(); count];
+        for (index, entry) in systems.iter().enumerate() {
+            for (other, other_entry) in systems.iter().enumerate() {
+                if entry.after.contains(&other_entry.label)
+                    || other_entry.before.contains(&entry.label)
+                {
+                    dependencies[index].push(other);
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(count);
+        let mut scheduled = vec![false; count];
+        while order.len() < count {
+            let next = (0..count).find(|&index| {
+                !scheduled[index]
+                    && dependencies[index]
+                        .iter()
+                        .all(|&dependency| scheduled[dependency])
+            });
+            match next {
+                Some(index) => {
+                    scheduled[index] = true;
+                    order.push(index);
+                }
+                None => {
+                    let cycle: Vec<_> = (0..count)
+                        .filter(|&index| !scheduled[index])
+                        .map(|index| systems[index].label)
+                        .collect();
+                    panic!("system ordering cycle between {cycle:?}");
+                }
+            }
+        }
+        order
+    }
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct SystemConfig<'a> {
+    entry: &'a mut SystemEntry,
+}
+
+impl SystemConfig<'_> {
+    pub fn id(&self) -> SystemId {
+        self.entry.id
+    }
+    pub fn label(self, label: &'static str) -> Self {
+        self.entry.label = label;
+        self
+    }
+    pub fn before(self, label: &'static str) -> Self {
+        self.entry.before.push(label);
+        self
+    }
+    pub fn after(self, label: &'static str) -> Self {
+        self.entry.after.push(label);
+        self
+    }
+    /// Puts the system in `set`, replacing any set it was in.
+    pub fn in_set(self, set: &'static str) -> Self {
+        self.entry.set = Some(set);
+        self
+    }
+    /// Skips the system on any run where `condition` returns false.
+    pub fn run_if(self, condition: impl Fn(&World) -> bool + 'static) -> Self {
+        self.entry.conditions.push(Box::new(condition));
+        self
+    }
+}
+
+/// Two systems of one schedule that couldn't run at the same time, from
+/// `App::conflict_report`. Displays as
+/// `Update: movement <-> collision on [Position]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemConflict {
+    pub schedule: &'static str,
+    pub first: &'static str,
+    pub second: &'static str,
+    /// Component and resource type names written by one and accessed by
+    /// the other.
+    pub types: Vec<&'static str>,
+}
+
+impl fmt::Display for SystemConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} <-> {} on [{}]",
+            self.schedule,
+            self.first,
+            self.second,
+            self.types.join(", ")
+        )
+    }
+}
+
+/// A reusable bundle of systems and world setup, added with
+/// `App::add_plugin`:
+///
+/// ```ignore
+/// struct PhysicsPlugin;
+///
+/// impl Plugin for PhysicsPlugin {
+///     fn build(&self, app: &mut App) {
+///         app.add_startup_system(|world: &mut World| {
+///             world.register_component::<Collide>();
+///             world.insert_resource(Gravity(9.8));
+///         });
+///         app.add_system(physics_system);
+///     }
+/// }
+/// ```
+pub trait Plugin {
+    fn build(&self, app: &mut App);
+}
+
+/// A tuple of systems for `App::add_system_set`.
+pub trait SystemSet<Params> {
+    fn add_to_set(self, app: &mut App, set: &'static str);
+}
+
+macro_rules! impl_system_set {
+    ($(($system:ident, $params:ident)),+) => {
+        impl<$($params, $system: System<$params> + 'static),+> SystemSet<($($params,)+)>
+            for ($($system,)+)
+        {
+            #[allow(non_snake_case)]
+            fn add_to_set(self, app: &mut App, set: &'static str) {
+                let ($($system,)+) = self;
+                $(app.add_system($system).in_set(set);)+
+            }
+        }
+    };
+}
+
+impl_system_set!((A, PA));
+impl_system_set!((A, PA), (B, PB));
+impl_system_set!((A, PA), (B, PB), (C, PC));
+impl_system_set!((A, PA), (B, PB), (C, PC), (D, PD));
+impl_system_set!((A, PA), (B, PB), (C, PC), (D, PD), (E, PE));
+impl_system_set!((A, PA), (B, PB), (C, PC), (D, PD), (E, PE), (G, PG));
+
+#[cfg(test)]
+mod tests {
+    use crate::{App, Changed, Commands, Component, EntityId, Res, ResMut, World};
+
+    #[derive(Debug)]
+    struct A(u32);
+    impl Component for A {}
+
+    #[derive(Default)]
+    struct Seen(Vec<usize>);
+
+    #[test]
+    fn changed_reader_before_writer_misses_writes_of_the_same_frame() {
+        let mut world = World::new();
+        world.spawn().insert(A(0));
+        world.insert_resource(Seen::default());
+        let mut app = App::new();
+        app.add_system(|changed: Vec<Changed<A>>, mut seen: ResMut<Seen>| {
+            seen.0.push(changed.len());
+        })
+        .label("reader");
+        app.add_system(|values: Vec<&mut A>| {
+            for value in values {
+                value.0 += 1;
+            }
+        })
+        .after("reader");
+        for _ in 0..5 {
+            app.run_once(&mut world);
+        }
+        assert_eq!(world.resource::<Seen>().0, vec![1, 0, 0, 0, 0]);
+    }
+
+    #[derive(Default)]
+    struct SeenToo(Vec<usize>);
+
+    #[test]
+    fn two_readers_see_a_change_once() {
+        let mut world = World::new();
+        let id = world.spawn().insert(A(0)).id();
+        world.insert_resource(Seen::default());
+        world.insert_resource(SeenToo::default());
+        let mut app = App::new();
+        app.add_system(|changed: Vec<Changed<A>>, mut seen: ResMut<Seen>| {
+            seen.0.push(changed.len());
+        });
+        app.add_system(|changed: Vec<Changed<A>>, mut seen: ResMut<SeenToo>| {
+            seen.0.push(changed.len());
+        });
+        app.run_once(&mut world);
+        world.get_component_mut::<A>(id).unwrap().0 += 1;
+        app.run_once(&mut world);
+        app.run_once(&mut world);
+        assert_eq!(world.resource::<Seen>().0, vec![1, 1, 0]);
+        assert_eq!(world.resource::<SeenToo>().0, vec![1, 1, 0]);
+    }
+
+    #[test]
+    fn changed_reader_doesnt_see_its_own_writes_again() {
+        let mut world = World::new();
+        world.spawn().insert(A(0));
+        world.insert_resource(Seen::default());
+        let mut app = App::new();
+        app.add_system(
+            |changed: Vec<(&mut A, Changed<A>)>, mut seen: ResMut<Seen>| {
+                seen.0.push(changed.len());
+            },
+        );
+        for _ in 0..3 {
+            app.run_once(&mut world);
+        }
+        assert_eq!(world.resource::<Seen>().0, vec![1, 0, 0]);
+    }
+
+    #[derive(Default)]
+    struct Spawned(Option<EntityId>);
+
+    #[test]
+    fn later_system_inserts_onto_an_earlier_spawn() {
+        let mut world = World::new();
+        world.insert_resource(Spawned::default());
+        let mut app = App::new();
+        app.add_system(|commands: Commands, mut spawned: ResMut<Spawned>| {
+            spawned.0 = Some(commands.spawn().id());
+        })
+        .label("spawner");
+        app.add_system(|commands: Commands, spawned: Res<Spawned>| {
+            commands.entity(spawned.0.unwrap()).insert(A(7));
+        })
+        .after("spawner");
+        app.run_once(&mut world);
+        let id = world.resource::<Spawned>().0.unwrap();
+        assert!(world.is_alive(id));
+        assert_eq!(world.get_component::<A>(id).map(|a| a.0), Some(7));
+    }
+
+    #[test]
+    fn systems_can_capture_owned_state() {
+        struct Config {
+            multiplier: u32,
+        }
+        let mut world = World::new();
+        world.spawn().insert(A(2));
+        world.spawn().insert(A(5));
+        world.insert_resource(Seen::default());
+        let config = Config { multiplier: 3 };
+        let mut app = App::new();
+        app.add_system(move |values: Vec<&A>, mut seen: ResMut<Seen>| {
+            let total: u32 = values.iter().map(|value| value.0 * config.multiplier).sum();
+            seen.0.push(total as usize);
+        });
+        app.run_once(&mut world);
+        assert_eq!(world.resource::<Seen>().0, vec![21]);
+    }
+
+    #[test]
+    fn removed_system_stops_running() {
+        let mut world = World::new();
+        world.insert_resource(Seen::default());
+        let mut app = App::new();
+        let id = app.add_system(|mut seen: ResMut<Seen>| seen.0.push(1)).id();
+        app.add_system(|mut seen: ResMut<Seen>| seen.0.push(2));
+        app.run_once(&mut world);
+        assert!(app.remove_system(id));
+        app.run_once(&mut world);
+        assert!(!app.remove_system(id));
+        assert_eq!(world.resource::<Seen>().0, vec![1, 2, 2]);
+    }
+
+    #[derive(Default)]
+    struct StartupRuns(u32);
+
+    #[test]
+    fn startup_systems_run_once() {
+        let mut world = World::new();
+        world.insert_resource(StartupRuns::default());
+        let mut app = App::new();
+        app.add_startup_system(|world: &mut World| {
+            world.resource_mut::<StartupRuns>().0 += 1;
+            world.spawn().insert(A(1));
+        });
+        app.add_startup_system(|commands: Commands, mut runs: ResMut<StartupRuns>| {
+            runs.0 += 1;
+            commands.spawn().insert(A(2));
+        });
+        for _ in 0..3 {
+            app.run_once(&mut world);
+        }
+        assert_eq!(world.resource::<StartupRuns>().0, 2);
+        assert_eq!(world.component_count::<A>(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "mutably while also accessing it elsewhere")]
+    fn aliasing_startup_systems_are_rejected() {
+        let mut app = App::new();
+        app.add_startup_system(|_: Vec<&mut A>, _: Vec<&A>| {});
+    }
+
+    #[test]
+    fn named_schedules_run_on_their_own() {
+        let mut world = World::new();
+        world.insert_resource(Seen::default());
+        let mut app = App::new();
+        app.add_system_to_schedule("Load", |commands: Commands, mut seen: ResMut<Seen>| {
+            seen.0.push(1);
+            commands.spawn().insert(A(1));
+        });
+        app.add_system(|mut seen: ResMut<Seen>| seen.0.push(2));
+        app.run_schedule("Load", &mut world);
+        assert_eq!(world.resource::<Seen>().0, vec![1]);
+        assert_eq!(world.component_count::<A>(), 1);
+        app.run_schedule("Missing", &mut world);
+        app.run_once(&mut world);
+        assert_eq!(world.resource::<Seen>().0, vec![1, 2]);
+    }
+}
@@ -0,0 +1,52 @@
+use crate::world::World;
+use std::any::TypeId;
+use std::collections::HashMap;
+
+/// One distinct set of component types and how many entities have exactly
+/// that set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchetypeInfo {
+    component_types: Vec<TypeId>,
+    component_names: Vec<&'static str>,
+    entity_count: usize,
+}
+
+impl ArchetypeInfo {
+    pub fn component_types(&self) -> &[TypeId] {
+        &self.component_types
+    }
+    /// Sorted, matching the order of `component_types`.
+    pub fn component_names(&self) -> &[&'static str] {
+        &self.component_names
+    }
+    pub fn entity_count(&self) -> usize {
+        self.entity_count
+    }
+}
+
+impl World {
+    /// Groups live entities by their exact component set, in order of each
+    /// set's first entity. Components live in per-type storages rather than
+    /// archetype tables, so this is computed on demand; it shows how
+    /// fragmented the combinations have become.
+    pub fn archetypes(&self) -> impl Iterator<Item = ArchetypeInfo> {
+        let mut archetypes: Vec<ArchetypeInfo> = Vec::new();
+        let mut indices: HashMap<Vec<TypeId>, usize> = HashMap::new();
+        for id in self.iter_entities() {
+            let (component_types, component_names): (Vec<_>, Vec<_>) =
+                self.components_of(id).into_iter().unzip();
+            match indices.get(&component_types) {
+                Some(&index) => archetypes[index].entity_count += 1,
+                None => {
+                    indices.insert(component_types.clone(), archetypes.len());
+                    archetypes.push(ArchetypeInfo {
+                        component_types,
+                        component_names,
+                        entity_count: 1,
+                    });
+                }
+            }
+        }
+        archetypes.into_iter()
+    }
+}
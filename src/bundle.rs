@@ -0,0 +1,60 @@
+use crate::component::Component;
+use crate::world::{EntityId, World};
+use std::any::TypeId;
+
+pub trait Bundle: Sized {
+    fn insert_into(self, world: &mut World, id: EntityId);
+    /// Inserts `bundles[i]` onto `ids[i]`, touching each storage once for the
+    /// whole batch.
+    fn insert_batch(world: &mut World, ids: &[EntityId], bundles: Vec<Self>);
+    /// Reserves room for `additional` more of each component in the bundle.
+    fn reserve(world: &mut World, additional: usize);
+}
+
+impl<T: Component> Bundle for T {
+    fn insert_into(self, world: &mut World, id: EntityId) {
+        world.add_component(id, self);
+    }
+    fn insert_batch(world: &mut World, ids: &[EntityId], bundles: Vec<Self>) {
+        let tick = world.change_tick();
+        let storage = world.storage_or_insert::<T>();
+        for (&id, component) in ids.iter().zip(bundles) {
+            storage.insert(id, component, tick);
+        }
+        for &id in ids {
+            world.trigger_insert(TypeId::of::<T>(), id);
+        }
+    }
+    fn reserve(world: &mut World, additional: usize) {
+        world.reserve_components::<T>(additional);
+    }
+}
+
+macro_rules! impl_bundle {
+    ($(($bundle:ident, $index:tt)),+) => {
+        impl<$($bundle: Bundle),+> Bundle for ($($bundle,)+) {
+            #[allow(non_snake_case)]
+            fn insert_into(self, world: &mut World, id: EntityId) {
+                let ($($bundle,)+) = self;
+                $($bundle.insert_into(world, id);)+
+            }
+            fn insert_batch(world: &mut World, ids: &[EntityId], bundles: Vec<Self>) {
+                let mut columns = ($(Vec::<$bundle>::with_capacity(bundles.len()),)+);
+                for bundle in bundles {
+                    $(columns.$index.push(bundle.$index);)+
+                }
+                $($bundle::insert_batch(world, ids, columns.$index);)+
+            }
+            fn reserve(world: &mut World, additional: usize) {
+                $($bundle::reserve(world, additional);)+
+            }
+        }
+    };
+}
+
+impl_bundle!((A, 0));
+impl_bundle!((A, 0), (B, 1));
+impl_bundle!((A, 0), (B, 1), (C, 2));
+impl_bundle!((A, 0), (B, 1), (C, 2), (D, 3));
+impl_bundle!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4));
+impl_bundle!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (G, 5));
@@ -0,0 +1,147 @@
+use crate::component::Component;
+use crate::world::{EntityId, World};
+use std::cell::RefCell;
+
+pub(crate) type Command = Box<dyn FnOnce(&mut World)>;
+
+/// Structural changes queued from a system, which only sees `&World`. The
+/// queue is applied by `App` after each schedule run (after `UPDATE`, after
+/// every `FIXED_UPDATE` step and after `RENDER`), or by
+/// `World::apply_commands`.
+///
+/// Commands apply in the order they were queued: those of earlier systems
+/// first, and each system's own in the order it queued them. So a command
+/// queued after a `spawn`, by the same system or a later one, already sees
+/// the spawned entity.
+pub struct Commands<'w> {
+    world: &'w World,
+}
+
+impl<'w> Commands<'w> {
+    pub fn add(&self, command: impl FnOnce(&mut World) + 'static) {
+        self.world
+            .command_queue
+            .borrow_mut()
+            .push(Box::new(command));
+    }
+    /// Queues spawning an empty entity. Its id is reserved right away, so
+    /// it can be handed to other entities or systems before the spawn is
+    /// applied.
+    pub fn spawn(&self) -> EntityCommands<'w> {
+        let id = self.world.reserve_id();
+        self.add(move |world| world.register_reserved(id));
+        self.entity(id)
+    }
+    /// Queues changes to an existing entity. If the entity is gone by the
+    /// time they're applied, they do nothing.
+    pub fn entity(&self, id: EntityId) -> EntityCommands<'w> {
+        EntityCommands {
+            queue: &self.world.command_queue,
+            id,
+        }
+    }
+}
+
+pub struct EntityCommands<'w> {
+    queue: &'w RefCell<Vec<Command>>,
+    id: EntityId,
+}
+
+impl EntityCommands<'_> {
+    pub fn id(&self) -> EntityId {
+        self.id
+    }
+    pub fn insert<T: Component>(self, component: T) -> Self {
+        self.add(move |world, id| {
+            world.add_component(id, component);
+        })
+    }
+    pub fn remove<T: Component>(self) -> Self {
+        self.add(|world, id| {
+            world.remove_component::<T>(id);
+        })
+    }
+    pub fn despawn(self) {
+        self.add(|world, id| {
+            world.despawn(id);
+        });
+    }
+    fn add(self, command: impl FnOnce(&mut World, EntityId) + 'static) -> Self {
+        let id = self.id;
+        self.queue
+            .borrow_mut()
+            .push(Box::new(move |world: &mut World| {
+                if world.is_alive(id) {
+                    command(world, id);
+                }
+            }));
+        self
+    }
+}
+
+impl World {
+    pub fn commands(&self) -> Commands<'_> {
+        Commands { world: self }
+    }
+    /// Runs every queued command in the order it was queued.
+    pub fn apply_commands(&mut self) {
+        loop {
+            let queued = std::mem::take(self.command_queue.get_mut());
+            if queued.is_empty() {
+                break;
+            }
+            for command in queued {
+                command(self);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Component, World};
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+    impl Component for Health {}
+
+    #[test]
+    fn spawn_and_later_insert_in_one_frame() {
+        let mut world = World::new();
+        let commands = world.commands();
+        let id = commands.spawn().id();
+        commands.entity(id).insert(Health(5));
+        assert!(!world.is_alive(id));
+        world.apply_commands();
+        assert_eq!(world.get_component::<Health>(id), Some(&Health(5)));
+    }
+
+    #[test]
+    fn commands_on_a_despawned_entity_do_nothing() {
+        let mut world = World::new();
+        let id = world.spawn().insert(Health(1)).id();
+        let commands = world.commands();
+        commands.entity(id).insert(Health(2)).remove::<Health>();
+        commands.entity(id).despawn();
+        world.despawn(id);
+        let reused = world.spawn().id();
+        world.apply_commands();
+        assert!(world.is_alive(reused));
+        assert!(!world.is_alive(id));
+        assert_eq!(world.validate(), vec![]);
+    }
+
+    #[test]
+    fn entity_commands_apply_in_queued_order() {
+        let mut world = World::new();
+        let id = world.spawn().id();
+        let commands = world.commands();
+        commands
+            .entity(id)
+            .insert(Health(1))
+            .remove::<Health>()
+            .insert(Health(3));
+        world.apply_commands();
+        assert_eq!(world.get_component::<Health>(id), Some(&Health(3)));
+    }
+}
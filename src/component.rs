@@ -0,0 +1,40 @@
+use crate::world::{EntityId, World};
+
+pub trait Component: 'static {
+    /// False for components that may only be replaced, never edited in
+    /// place, like `Name`, whose lookup index would go stale otherwise.
+    /// Borrowing one mutably (`get_component_mut`, `&mut T` or `Mut<T>`
+    /// queries, ...) then fails to build.
+    const MUTABLE: bool = true;
+}
+
+/// Stops `T` from being borrowed mutably when it isn't `MUTABLE`; use as
+/// `const { assert_mutable::<T>() }` so it fails at build time.
+pub(crate) const fn assert_mutable<T: Component>() {
+    assert!(
+        T::MUTABLE,
+        "this component can't be borrowed mutably; insert a new value instead"
+    );
+}
+
+/// A tuple of component types, for APIs that take several types at once.
+pub trait ComponentSet {
+    fn any_present(world: &World, id: EntityId) -> bool;
+}
+
+macro_rules! impl_component_set {
+    ($($member:ident),+) => {
+        impl<$($member: Component),+> ComponentSet for ($($member,)+) {
+            fn any_present(world: &World, id: EntityId) -> bool {
+                $(world.has_component::<$member>(id))||+
+            }
+        }
+    };
+}
+
+impl_component_set!(A);
+impl_component_set!(A, B);
+impl_component_set!(A, B, C);
+impl_component_set!(A, B, C, D);
+impl_component_set!(A, B, C, D, E);
+impl_component_set!(A, B, C, D, E, G);
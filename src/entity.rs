@@ -0,0 +1,103 @@
+use crate::component::Component;
+use crate::query::{ComponentCombination, Ref};
+use crate::world::{EntityId, World};
+
+/// Read-only view of one live entity.
+///
+/// Like query items, everything it hands out borrows the world for `'w`
+/// rather than the view itself: the references may outlive the
+/// `EntityRef`, but not the `&World` it came from, so no `&mut World` can
+/// change a component while someone still reads it.
+pub struct EntityRef<'w> {
+    world: &'w World,
+    id: EntityId,
+}
+
+impl<'w> EntityRef<'w> {
+    pub fn id(&self) -> EntityId {
+        self.id
+    }
+    pub fn get<T: Component>(&self) -> Option<&'w T> {
+        self.world.get_component::<T>(self.id)
+    }
+    /// Like `get`, also telling whether the component changed; see `Ref`.
+    pub fn get_ref<T: Component>(&self) -> Option<Ref<'w, T>> {
+        Ref::<T>::filter(self.world, self.id)
+    }
+    pub fn contains<T: Component>(&self) -> bool {
+        self.world.has_component::<T>(self.id)
+    }
+    /// Type names of every component the entity has, sorted.
+    pub fn components(&self) -> Vec<&'static str> {
+        self.world.component_type_names(self.id)
+    }
+}
+
+/// Mutable view of one live entity, also what `spawn` returns; `insert`
+/// and `remove` chain, so an entity can be built and its id kept in one go:
+///
+/// ```ignore
+/// let wall = world.spawn().insert(Collide {}).insert(MoveTo {}).id();
+/// ```
+pub struct EntityMut<'w> {
+    world: &'w mut World,
+    id: EntityId,
+}
+
+impl EntityMut<'_> {
+    pub fn id(&self) -> EntityId {
+        self.id
+    }
+    pub fn get<T: Component>(&self) -> Option<&T> {
+        self.world.get_component::<T>(self.id)
+    }
+    pub fn get_mut<T: Component>(&mut self) -> Option<&mut T> {
+        self.world.get_component_mut::<T>(self.id)
+    }
+    pub fn contains<T: Component>(&self) -> bool {
+        self.world.has_component::<T>(self.id)
+    }
+    pub fn components(&self) -> Vec<&'static str> {
+        self.world.component_type_names(self.id)
+    }
+    /// Replaces any `T` the entity already has.
+    pub fn insert<T: Component>(self, component: T) -> Self {
+        self.world.add_component(self.id, component);
+        self
+    }
+    /// Keeps the entity's existing `T`, if any, dropping `component`.
+    pub fn insert_if_absent<T: Component>(self, component: T) -> Self {
+        let _ = self.world.insert_if_absent(self.id, component);
+        self
+    }
+    /// Drops the entity's `T`, if it has one.
+    pub fn remove<T: Component>(self) -> Self {
+        self.world.remove_component::<T>(self.id);
+        self
+    }
+}
+
+impl World {
+    /// Spawns an entity with no components and returns it for adding some.
+    pub fn spawn(&mut self) -> EntityMut<'_> {
+        let id = self.reserve_entity();
+        EntityMut { world: self, id }
+    }
+    /// `None` if `id` isn't alive.
+    pub fn entity(&self, id: EntityId) -> Option<EntityRef<'_>> {
+        self.is_alive(id).then_some(EntityRef { world: self, id })
+    }
+    /// `None` if `id` isn't alive.
+    pub fn entity_mut(&mut self, id: EntityId) -> Option<EntityMut<'_>> {
+        self.is_alive(id).then_some(EntityMut { world: self, id })
+    }
+    /// Despawns every live entity for which `keep` returns false, e.g. all
+    /// bullets that left the screen. Returns how many were despawned.
+    pub fn retain_entities(&mut self, mut keep: impl FnMut(EntityRef<'_>) -> bool) -> usize {
+        let doomed: Vec<EntityId> = self
+            .iter_entities()
+            .filter(|&id| !keep(EntityRef { world: self, id }))
+            .collect();
+        doomed.into_iter().filter(|&id| self.despawn(id)).count()
+    }
+}
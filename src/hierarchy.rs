@@ -0,0 +1,102 @@
+use crate::component::Component;
+use crate::world::{EntityId, World};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Parent(pub EntityId);
+impl Component for Parent {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Children(pub Vec<EntityId>);
+impl Component for Children {}
+
+impl World {
+    /// Moves `child` under `parent`, detaching it from its previous parent
+    /// first so both `Children` lists stay in sync. Returns false, changing
+    /// nothing, if either isn't alive or `parent` is `child` or one of its
+    /// descendants, which would make a cycle.
+    pub fn set_parent(&mut self, child: EntityId, parent: EntityId) -> bool {
+        if !self.is_alive(child) || !self.is_alive(parent) || self.is_ancestor(child, parent) {
+            return false;
+        }
+        self.remove_parent(child);
+        self.add_component(child, Parent(parent));
+        match self.get_component_mut::<Children>(parent) {
+            Some(children) => children.0.push(child),
+            None => {
+                self.add_component(parent, Children(vec![child]));
+            }
+        }
+        true
+    }
+    /// Whether `ancestor` is `id` or above it in the hierarchy.
+    fn is_ancestor(&self, ancestor: EntityId, id: EntityId) -> bool {
+        let mut current = Some(id);
+        while let Some(next) = current {
+            if next == ancestor {
+                return true;
+            }
+            current = self
+                .get_component::<Parent>(next)
+                .map(|&Parent(parent)| parent);
+        }
+        false
+    }
+    /// Returns the parent `child` was detached from, if it had one.
+    pub fn remove_parent(&mut self, child: EntityId) -> Option<EntityId> {
+        let Parent(parent) = self.remove_component::<Parent>(child)?;
+        if let Some(children) = self.get_component_mut::<Children>(parent) {
+            children.0.retain(|&sibling| sibling != child);
+            if children.0.is_empty() {
+                self.remove_component::<Children>(parent);
+            }
+        }
+        Some(parent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Children, Parent, World};
+
+    #[test]
+    fn set_parent_links_both_ways() {
+        let mut world = World::new();
+        let parent = world.spawn().id();
+        let child = world.spawn().id();
+        assert!(world.set_parent(child, parent));
+        assert_eq!(
+            world.get_component::<Parent>(child).map(|p| p.0),
+            Some(parent)
+        );
+        assert_eq!(
+            world.get_component::<Children>(parent).map(|c| c.0.clone()),
+            Some(vec![child])
+        );
+        assert_eq!(world.validate(), vec![]);
+    }
+
+    #[test]
+    fn set_parent_rejects_dead_entities() {
+        let mut world = World::new();
+        let alive = world.spawn().id();
+        let dead = world.spawn().id();
+        world.despawn(dead);
+        assert!(!world.set_parent(dead, alive));
+        assert!(!world.set_parent(alive, dead));
+        assert!(!world.has_component::<Children>(alive));
+        assert!(!world.has_component::<Parent>(alive));
+        assert_eq!(world.validate(), vec![]);
+    }
+
+    #[test]
+    fn set_parent_rejects_cycles() {
+        let mut world = World::new();
+        let root = world.spawn().id();
+        let child = world.spawn().id();
+        assert!(!world.set_parent(root, root));
+        assert!(world.set_parent(child, root));
+        assert!(!world.set_parent(root, child));
+        assert!(!world.has_component::<Parent>(root));
+        assert_eq!(world.validate(), vec![]);
+    }
+}
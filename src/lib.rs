@@ -0,0 +1,57 @@
+mod app;
+mod archetype;
+mod bundle;
+mod command;
+mod component;
+mod entity;
+mod hierarchy;
+mod name;
+mod observer;
+mod pool;
+mod profile;
+mod query;
+mod query_state;
+mod registry;
+mod resource;
+mod rng;
+#[cfg(feature = "serde")]
+mod serialize;
+mod snapshot;
+mod storage;
+mod system;
+mod time;
+#[cfg(any(debug_assertions, test))]
+mod validate;
+mod world;
+
+// Lets `#[system]`, which names `::test_rust`, be used in unit tests.
+#[cfg(test)]
+extern crate self as test_rust;
+
+pub use app::{App, Plugin, ShouldExit, SystemConfig, SystemConflict, SystemId, SystemSet};
+pub use archetype::ArchetypeInfo;
+pub use bundle::Bundle;
+pub use command::{Commands, EntityCommands};
+pub use component::{Component, ComponentSet};
+pub use entity::{EntityMut, EntityRef};
+pub use hierarchy::{Children, Parent};
+pub use name::Name;
+pub use pool::{Pool, PoolPolicy};
+pub use profile::{SystemProfile, SystemTiming};
+pub use query::{
+    get_components, get_components_mut, query_unchecked, Changed, ComponentCombination, Filtered,
+    Mut, Or, Query, QueryAccess, QueryCombinations, QueryError, QueryIter, QuerySingleError,
+    ReadOnlyComponentCombination, Ref, With, WithEntities, Without,
+};
+pub use query_state::QueryState;
+pub use registry::{ComponentId, ComponentInfo};
+pub use resource::{Local, Res, ResMut};
+pub use rng::Rng;
+pub use snapshot::WorldSnapshot;
+pub use storage::StorageKind;
+pub use system::{QueryWarnings, StartupSystem, System, SystemAccess, SystemParam};
+pub use test_rust_macros::system;
+pub use time::Time;
+#[cfg(any(debug_assertions, test))]
+pub use validate::ConsistencyError;
+pub use world::{EntityError, EntityId, ParseEntityIdError, World};
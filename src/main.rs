@@ -1,158 +1,74 @@
-use once_cell::sync::OnceCell;
-use sparseset::SparseSet;
-use std::sync::atomic::AtomicUsize;
+use std::time::Instant;
+use test_rust::*;
 
-struct Entity {
-    id: usize,
-    drop_functions: Vec<Box<dyn FnOnce()>>,
+fn new_player(world: &mut World) -> EntityId {
+    world.spawn_with_defaults::<(Collide, MoveTo)>()
 }
 
-static mut ID: AtomicUsize = AtomicUsize::new(0);
-impl Entity {
-    fn new() -> Self {
-        Self {
-            id: unsafe { ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed) },
-            drop_functions: Vec::new(),
-        }
-    }
-    fn add_component<T: Component>(mut self, component: T) -> Self {
-        let id = self.id;
-        component.insert(id);
-        self.drop_functions.push(Box::new(move || T::drop(id)));
-        self
-    }
-}
-
-impl Drop for Entity {
-    fn drop(&mut self) {
-        for drop_function in self.drop_functions.drain(..) {
-            drop_function();
-        }
-    }
-}
-
-trait Component {
-    fn insert(self, id: usize);
-    fn drop(id: usize);
-}
-
-fn new_player() -> Entity {
-    Entity::new()
-        .add_component(Collide {})
-        .add_component(MoveTo {})
-}
-
-fn new_wall() -> Entity {
-    Entity::new().add_component(Collide {})
-}
-
-trait ComponentCombination {
-    fn filter(entity: &Entity) -> Option<Self>
-    where
-        Self: Sized;
+fn new_wall(world: &mut World) -> EntityId {
+    world.spawn().insert(Collide {}).id()
 }
 
+#[derive(Debug, Default)]
 struct Collide {}
 impl Collide {
     fn collide(&self) {
         println!("collide");
     }
 }
-static mut COLLIDES: OnceCell<SparseSet<Collide>> = OnceCell::new();
-impl Component for Collide {
-    fn insert(self, id: usize) {
-        unsafe {
-            COLLIDES.get_or_init(|| SparseSet::with_capacity(2048));
-            COLLIDES.get_mut().unwrap().insert(id, self);
-        }
-    }
-
-    fn drop(id: usize) {
-        unsafe {
-            COLLIDES.get_mut().unwrap().remove(id);
-        }
-    }
-}
-
-impl ComponentCombination for &Collide {
-    fn filter(entity: &Entity) -> Option<Self> {
-        unsafe {
-            COLLIDES
-                .get_or_init(|| SparseSet::with_capacity(2048))
-                .get(entity.id)
-        }
-    }
-}
-impl ComponentCombination for &mut Collide {
-    fn filter(entity: &Entity) -> Option<Self> {
-        unsafe {
-            COLLIDES.get_or_init(|| SparseSet::with_capacity(2048));
-            COLLIDES.get_mut().unwrap().get_mut(entity.id)
-        }
-    }
-}
+impl Component for Collide {}
 
+#[derive(Debug, Default)]
 struct MoveTo {}
 impl MoveTo {
     fn move_to(&self) {
         println!("move_to");
     }
 }
-static mut MOVE_TOS: OnceCell<SparseSet<MoveTo>> = OnceCell::new();
-impl Component for MoveTo {
-    fn insert(self, id: usize) {
-        unsafe {
-            MOVE_TOS.get_or_init(|| SparseSet::with_capacity(2048));
-            MOVE_TOS.get_mut().unwrap().insert(id, self);
-        }
-    }
+impl Component for MoveTo {}
 
-    fn drop(id: usize) {
-        unsafe {
-            MOVE_TOS.get_mut().unwrap().remove(id);
-        }
-    }
-}
-impl ComponentCombination for &MoveTo {
-    fn filter(entity: &Entity) -> Option<Self> {
-        unsafe {
-            MOVE_TOS
-                .get_or_init(|| SparseSet::with_capacity(2048))
-                .get(entity.id)
-        }
-    }
-}
-impl ComponentCombination for &mut MoveTo {
-    fn filter(entity: &Entity) -> Option<Self> {
-        unsafe {
-            MOVE_TOS.get_or_init(|| SparseSet::with_capacity(2048));
-            MOVE_TOS.get_mut().unwrap().get_mut(entity.id)
-        }
-    }
+#[derive(PartialEq)]
+enum GameState {
+    Playing,
+    Paused,
 }
+
 fn main() {
-    let entities = vec![new_player(), new_wall()];
+    let mut world = World::new();
+    world.register_component::<Collide>();
+    world.register_component::<MoveTo>();
+    new_player(&mut world);
+    new_wall(&mut world);
+    world.insert_resource(GameState::Playing);
+    print!("{}", world.debug_dump());
 
-    let collides = get_components::<&Collide>(&entities);
+    let collides = get_components::<&Collide>(&world).unwrap();
     println!("-Collide- {}", collides.len());
     for collide in collides {
         collide.collide();
     }
 
-    let move_tos = get_components::<&MoveTo>(&entities);
+    let move_tos = get_components::<&MoveTo>(&world).unwrap();
     println!("-MoveTo- {}", move_tos.len());
     for move_to in move_tos {
         move_to.move_to();
     }
 
-    let collide_with_move_to = get_components::<(&Collide, &MoveTo)>(&entities);
+    let single_collides = get_components::<(&Collide,)>(&world).unwrap();
+    println!("-(Collide,)- {}", single_collides.len());
+    for (collide,) in single_collides {
+        collide.collide();
+    }
+
+    let collide_with_move_to = get_components::<(&Collide, &MoveTo)>(&world).unwrap();
     println!("-Collide with MoveTo- {}", collide_with_move_to.len());
     for (collide, move_to) in collide_with_move_to {
         collide.collide();
         move_to.move_to();
     }
 
-    let collide_mut_with_move_to = get_components::<(&mut Collide, &MoveTo)>(&entities);
+    let collide_mut_with_move_to =
+        get_components_mut::<(&mut Collide, &MoveTo)>(&mut world).unwrap();
     println!(
         "-Collide mut with MoveTo- {}",
         collide_mut_with_move_to.len()
@@ -162,40 +78,137 @@ fn main() {
         move_to.move_to();
     }
 
+    // Different component types live in different storages, so both can be
+    // borrowed mutably at once; only `(&mut Collide, &Collide)` is rejected.
+    let collide_mut_with_move_to_mut =
+        get_components_mut::<(&mut Collide, &mut MoveTo)>(&mut world).unwrap();
+    println!(
+        "-Collide mut with MoveTo mut- {}",
+        collide_mut_with_move_to_mut.len()
+    );
+    for (collide, move_to) in collide_mut_with_move_to_mut {
+        collide.collide();
+        move_to.move_to();
+    }
+
     let mut app = App::new();
-    app.add_system(simple_system);
-    app.add_system(simple_system2);
+    app.add_system(simple_system)
+        .label("collision")
+        .after("movement");
+    app.add_system(simple_system2)
+        .label("movement")
+        .run_if(|world: &World| *world.resource::<GameState>() == GameState::Playing);
     app.add_system(simple_system3);
+    app.add_system(simple_system4);
+    app.add_system(physics_system);
+    app.add_system(render_system).after("collision");
+    let difficulty = 1.5;
+    app.add_system(move |collides: Vec<&Collide>| {
+        println!("difficulty_system x{difficulty} {}", collides.len());
+    })
+    .label("difficulty");
+    println!(
+        "-physics_system conflicts with simple_system4- {}",
+        physics_system_access().conflicts_with(&simple_system4_access())
+    );
 
-    app.run(&entities);
-}
+    for conflict in app.conflict_report() {
+        println!("-conflict- {conflict}");
+    }
 
-struct App {
-    systems: Vec<Box<dyn Fn(&Vec<Entity>)>>,
+    app.enable_profiling(true);
+    app.run_once(&mut world);
+
+    *world.resource_mut::<GameState>() = GameState::Paused;
+    app.run_once(&mut world);
+    print!("{}", *world.resource::<SystemProfile>());
+
+    bench_spawn();
+    bench_count();
+    bench_query_one();
+    #[cfg(feature = "rayon")]
+    bench_par_for_each();
 }
 
-impl App {
-    fn new() -> Self {
-        Self {
-            systems: Vec::new(),
-        }
+fn bench_spawn() {
+    const COUNT: usize = 100_000;
+
+    let mut world = World::new();
+    let start = Instant::now();
+    for _ in 0..COUNT {
+        world.spawn().insert(Collide {}).insert(MoveTo {});
     }
-    fn add_system<'a, T, F>(&'a mut self, system_func: F)
-    where
-        F: Fn(Vec<T>) + 'static,
-        T: ComponentCombination,
-    {
-        let wrapped_system_func = Box::new(move |entities: &Vec<Entity>| {
-            let components = get_components::<T>(entities);
-            system_func(components);
-        });
-        self.systems.push(wrapped_system_func);
+    println!("-spawn x{COUNT}- {:?}", start.elapsed());
+
+    let mut world = World::new();
+    let start = Instant::now();
+    world.spawn_batch((0..COUNT).map(|_| (Collide {}, MoveTo {})));
+    println!("-spawn_batch x{COUNT}- {:?}", start.elapsed());
+    println!("-memory x{COUNT}- {} bytes", world.memory_usage());
+}
+
+fn bench_count() {
+    const COUNT: usize = 100_000;
+
+    let mut world = World::new();
+    world.spawn_batch((0..COUNT).map(|_| (Collide {}, MoveTo {})));
+
+    let start = Instant::now();
+    let collected = get_components::<(&Collide, &MoveTo)>(&world).unwrap().len();
+    println!("-collect len x{collected}- {:?}", start.elapsed());
+
+    let start = Instant::now();
+    let counted = world.query::<(&Collide, &MoveTo)>().count();
+    println!("-count x{counted}- {:?}", start.elapsed());
+}
+
+fn bench_query_one() {
+    const COUNT: usize = 100_000;
+
+    let mut world = World::new();
+    let ids = world.spawn_batch((0..COUNT).map(|_| (Collide {}, MoveTo {})));
+    let player = ids[COUNT / 2];
+
+    let start = Instant::now();
+    let found = world
+        .query_mut::<(&mut Collide, &mut MoveTo)>()
+        .with_entities()
+        .find(|&(id, _)| id == player)
+        .is_some();
+    println!(
+        "-find by iterating x{COUNT} found:{found}- {:?}",
+        start.elapsed()
+    );
+
+    let start = Instant::now();
+    let found = world
+        .query_one_mut::<(&mut Collide, &mut MoveTo)>(player)
+        .is_some();
+    println!("-query_one_mut found:{found}- {:?}", start.elapsed());
+}
+
+#[cfg(feature = "rayon")]
+fn bench_par_for_each() {
+    use std::hint::black_box;
+    const COUNT: usize = 100_000;
+
+    fn work(move_to: &mut MoveTo) {
+        black_box(move_to);
+        black_box((0..1_000u64).fold(0, |sum, value| black_box(sum + value)));
     }
-    fn run(&self, entities: &Vec<Entity>) {
-        for system in &self.systems {
-            system(entities);
-        }
+
+    let mut world = World::new();
+    world.spawn_batch((0..COUNT).map(|_| MoveTo {}));
+
+    let start = Instant::now();
+    for move_to in world.query_mut::<&mut MoveTo>().iter_mut() {
+        work(move_to);
     }
+    println!("-for_each x{COUNT}- {:?}", start.elapsed());
+
+    let start = Instant::now();
+    world.query_mut::<&mut MoveTo>().par_for_each(work);
+    println!("-par_for_each x{COUNT}- {:?}", start.elapsed());
 }
 
 fn simple_system(collides: Vec<&Collide>) {
@@ -212,30 +225,34 @@ fn simple_system2(move_tos: Vec<&MoveTo>) {
     }
 }
 
-fn simple_system3(tuples: Vec<(&Collide, &MoveTo)>) {
+fn simple_system3(tuples: Query<(&Collide, &MoveTo)>) {
     println!("simple_system3");
-    for (collide, move_to) in tuples {
+    for (collide, move_to) in &tuples {
         collide.collide();
         move_to.move_to();
     }
 }
 
-fn get_components<'entity, T: ComponentCombination>(entities: &Vec<Entity>) -> Vec<T> {
-    let mut components = Vec::new();
-    for entity in entities {
-        if let Some(component) = T::filter(entity) {
-            components.push(component);
-        }
+#[system]
+fn simple_system4(collides: Vec<&Collide>, move_tos: Vec<&MoveTo>) {
+    println!("simple_system4");
+    for collide in collides {
+        collide.collide();
+    }
+    for move_to in move_tos {
+        move_to.move_to();
     }
-    components
 }
 
-impl<'entity, TA: ComponentCombination, TB: ComponentCombination> ComponentCombination
-    for (TA, TB)
-{
-    fn filter(entity: &Entity) -> Option<Self> {
-        let a = TA::filter(entity)?;
-        let b = TB::filter(entity)?;
-        Some((a, b))
+#[system]
+fn physics_system(collides: Vec<&mut Collide>, time: Res<Time>) {
+    println!("physics_system dt={}", time.delta_seconds());
+    for collide in collides {
+        collide.collide();
     }
 }
+
+fn render_system(move_tos: Vec<&MoveTo>, state: Res<GameState>) {
+    let paused = *state == GameState::Paused;
+    println!("render_system paused={paused} {}", move_tos.len());
+}
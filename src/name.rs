@@ -0,0 +1,127 @@
+use crate::component::Component;
+use crate::world::{EntityId, World};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A label for finding an entity with `World::find_by_name`, e.g. "Boss".
+///
+/// The name can't be borrowed mutably, so the lookup index can't go
+/// stale; insert a new `Name` to rename an entity.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Name(String);
+impl Component for Name {
+    const MUTABLE: bool = false;
+}
+
+impl Name {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Self(name)
+    }
+}
+
+/// Name → ids lookup, kept in sync as `Name` components come and go.
+#[derive(Default)]
+pub(crate) struct NameIndex {
+    ids: HashMap<String, Vec<EntityId>>,
+    names: HashMap<EntityId, String>,
+}
+
+impl NameIndex {
+    pub(crate) fn insert(&mut self, id: EntityId, name: &str) {
+        self.remove(id);
+        self.ids.entry(name.to_owned()).or_default().push(id);
+        self.names.insert(id, name.to_owned());
+    }
+    pub(crate) fn remove(&mut self, id: EntityId) {
+        let Some(name) = self.names.remove(&id) else {
+            return;
+        };
+        if let Some(ids) = self.ids.get_mut(&name) {
+            ids.retain(|&named| named != id);
+            if ids.is_empty() {
+                self.ids.remove(&name);
+            }
+        }
+    }
+    pub(crate) fn clear(&mut self) {
+        self.ids.clear();
+        self.names.clear();
+    }
+    fn get(&self, name: &str) -> &[EntityId] {
+        self.ids.get(name).map_or(&[], Vec::as_slice)
+    }
+}
+
+impl World {
+    /// Rebuilds the lookup index from the `Name` storage, after names were
+    /// replaced without going through insert observers.
+    pub(crate) fn reindex_names(&mut self) {
+        let named: Vec<(EntityId, String)> = self
+            .query_including_disabled::<&Name>()
+            .with_entities()
+            .map(|(id, name)| (id, name.0.clone()))
+            .collect();
+        self.names.clear();
+        for (id, name) in named {
+            self.names.insert(id, &name);
+        }
+    }
+    /// An entity whose `Name` is `name`. When several share it, this is the
+    /// one that got the name first; see `find_all_by_name` for all of them.
+    pub fn find_by_name(&self, name: &str) -> Option<EntityId> {
+        self.names.get(name).first().copied()
+    }
+    /// Every entity named `name`, in the order they got the name.
+    pub fn find_all_by_name(&self, name: &str) -> &[EntityId] {
+        self.names.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Name, World};
+
+    #[test]
+    fn find_by_name_follows_inserts_and_removals() {
+        let mut world = World::new();
+        let boss = world.spawn().insert(Name::new("Boss")).id();
+        assert_eq!(world.find_by_name("Boss"), Some(boss));
+        world.add_component(boss, Name::new("Defeated"));
+        assert_eq!(world.find_by_name("Boss"), None);
+        assert_eq!(world.find_by_name("Defeated"), Some(boss));
+        world.remove_component::<Name>(boss);
+        assert_eq!(world.find_by_name("Defeated"), None);
+    }
+
+    #[test]
+    fn map_component_reindexes_names() {
+        let mut world = World::new();
+        let first = world.spawn().insert(Name::new("a")).id();
+        let second = world.spawn().insert(Name::new("b")).id();
+        world.map_component::<Name, _>(|name| Name::new(name.as_str().to_uppercase()));
+        assert_eq!(world.find_by_name("a"), None);
+        assert_eq!(world.find_by_name("A"), Some(first));
+        assert_eq!(world.find_by_name("B"), Some(second));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::component::Component;
+use crate::name::Name;
+use crate::world::{EntityId, World};
+use std::any::TypeId;
+use std::rc::Rc;
+
+type Observer = Rc<dyn Fn(EntityId, &mut World)>;
+
+#[derive(Default)]
+pub(crate) struct Observers {
+    on_insert: Vec<Observer>,
+    on_remove: Vec<Observer>,
+}
+
+impl Observers {
+    pub(crate) fn has_remove_observers(&self) -> bool {
+        !self.on_remove.is_empty()
+    }
+}
+
+impl World {
+    /// Calls `observer` right after a `T` is inserted on an entity, including
+    /// when it replaces an existing one. Observers of the same type run in
+    /// registration order.
+    pub fn observe_insert<T: Component>(
+        &mut self,
+        observer: impl Fn(EntityId, &mut World) + 'static,
+    ) {
+        self.observers
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .on_insert
+            .push(Rc::new(observer));
+    }
+    /// Calls `observer` right before a `T` is removed from an entity, either
+    /// directly or by despawning it, so the component can still be read.
+    /// Each removal notifies once, even if an observer removes the `T` or
+    /// despawns the entity itself. Bulk removals (`remove_all`, `clear`)
+    /// don't notify.
+    pub fn observe_remove<T: Component>(
+        &mut self,
+        observer: impl Fn(EntityId, &mut World) + 'static,
+    ) {
+        self.observers
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .on_remove
+            .push(Rc::new(observer));
+    }
+    // Inserts and single removals all go through these two, so the name
+    // index is updated here; despawns and bulk removals update it directly.
+    pub(crate) fn trigger_insert(&mut self, type_id: TypeId, id: EntityId) {
+        if type_id == TypeId::of::<Name>() {
+            if let Some(name) = self.get_component::<Name>(id) {
+                let name = name.as_str().to_owned();
+                self.names.insert(id, &name);
+            }
+        }
+        let Some(observers) = self.observers.get(&type_id) else {
+            return;
+        };
+        // Cloned so observers can take `&mut World`, even to add observers.
+        for observer in observers.on_insert.clone() {
+            observer(id, self);
+        }
+    }
+    pub(crate) fn trigger_remove(&mut self, type_id: TypeId, id: EntityId) {
+        if type_id == TypeId::of::<Name>() {
+            self.names.remove(id);
+        }
+        let Some(observers) = self.observers.get(&type_id) else {
+            return;
+        };
+        if self.removing.contains(&(type_id, id)) {
+            return;
+        }
+        let observers = observers.on_remove.clone();
+        self.removing.push((type_id, id));
+        for observer in observers {
+            observer(id, self);
+        }
+        self.removing.retain(|&removing| removing != (type_id, id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Component, World};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[derive(Debug)]
+    struct A;
+    impl Component for A {}
+
+    #[derive(Debug)]
+    struct B;
+    impl Component for B {}
+
+    #[test]
+    fn remove_observer_may_despawn_its_entity() {
+        let mut world = World::new();
+        let calls = Rc::new(Cell::new(0));
+        let counted = calls.clone();
+        world.observe_remove::<B>(move |id, world| {
+            counted.set(counted.get() + 1);
+            world.despawn(id);
+        });
+        let id = world.spawn().insert(A).insert(B).id();
+        world.remove_component::<B>(id);
+        assert!(!world.is_alive(id));
+        assert_eq!(calls.get(), 1);
+        assert_eq!(world.validate(), vec![]);
+    }
+
+    #[test]
+    fn despawn_inside_despawn_frees_the_slot_once() {
+        let mut world = World::new();
+        let calls = Rc::new(Cell::new(0));
+        let counted = calls.clone();
+        world.observe_remove::<A>(move |id, world| {
+            counted.set(counted.get() + 1);
+            assert!(!world.despawn(id));
+        });
+        let id = world.spawn().insert(A).id();
+        assert!(world.despawn(id));
+        assert_eq!(calls.get(), 1);
+        let first = world.spawn().id();
+        let second = world.spawn().id();
+        assert_ne!(first.index(), second.index());
+        assert_eq!(world.validate(), vec![]);
+    }
+
+    #[test]
+    fn remove_observer_sees_the_component() {
+        let mut world = World::new();
+        let seen = Rc::new(Cell::new(false));
+        let saw = seen.clone();
+        world.observe_remove::<A>(move |id, world| {
+            saw.set(world.get_component::<A>(id).is_some());
+        });
+        let id = world.spawn().insert(A).id();
+        world.remove_component::<A>(id);
+        assert!(seen.get());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::bundle::Bundle;
+use crate::world::{EntityId, World};
+use std::collections::HashSet;
+use std::marker::PhantomData;
+
+/// What `Pool::acquire` does when every entity is in use.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PoolPolicy {
+    #[default]
+    ReturnNone,
+    Panic,
+}
+
+/// A fixed set of entities spawned up front and switched on and off with
+/// `set_enabled`, e.g. bullets or particles, so firing one doesn't spawn
+/// and despawn anything. Disabled entities don't show up in queries.
+///
+/// Released entities keep their components as they were, so reset whatever
+/// needs resetting after `acquire`.
+pub struct Pool<B> {
+    free: Vec<EntityId>,
+    active: HashSet<EntityId>,
+    policy: PoolPolicy,
+    marker: PhantomData<B>,
+}
+
+impl<B: Bundle + Default> Pool<B> {
+    /// Spawns `capacity` disabled entities with `B::default()`.
+    pub fn new(world: &mut World, capacity: usize) -> Self {
+        let free = (0..capacity)
+            .map(|_| {
+                let id = world.spawn_with_defaults::<B>();
+                world.set_enabled(id, false);
+                id
+            })
+            .collect();
+        Self {
+            free,
+            active: HashSet::new(),
+            policy: PoolPolicy::default(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<B> Pool<B> {
+    pub fn with_policy(mut self, policy: PoolPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+    /// Enables a free entity and returns it. When none is free, returns
+    /// `None` or panics, depending on the policy. Entities despawned while
+    /// in the pool are skipped and dropped from it.
+    pub fn acquire(&mut self, world: &mut World) -> Option<EntityId> {
+        while let Some(id) = self.free.pop() {
+            if world.is_alive(id) {
+                world.set_enabled(id, true);
+                self.active.insert(id);
+                return Some(id);
+            }
+        }
+        match self.policy {
+            PoolPolicy::ReturnNone => None,
+            PoolPolicy::Panic => {
+                panic!("pool exhausted: all {} entities in use", self.active.len())
+            }
+        }
+    }
+    /// Disables `id` and makes it free again. Returns false if it isn't an
+    /// active entity of this pool.
+    pub fn release(&mut self, world: &mut World, id: EntityId) -> bool {
+        if !self.active.remove(&id) {
+            return false;
+        }
+        world.set_enabled(id, false);
+        self.free.push(id);
+        true
+    }
+    pub fn active_count(&self) -> usize {
+        self.active.len()
+    }
+    pub fn free_count(&self) -> usize {
+        self.free.len()
+    }
+    pub fn is_active(&self, id: EntityId) -> bool {
+        self.active.contains(&id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pool, PoolPolicy};
+    use crate::{Component, World};
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Bullet(u32);
+    impl Component for Bullet {}
+
+    #[test]
+    fn released_entities_are_reused() {
+        let mut world = World::new();
+        let mut pool = Pool::<Bullet>::new(&mut world, 2);
+        assert_eq!(world.query::<&Bullet>().iter().count(), 0);
+        let first = pool.acquire(&mut world).unwrap();
+        let second = pool.acquire(&mut world).unwrap();
+        assert_eq!(pool.acquire(&mut world), None);
+        assert_eq!((pool.active_count(), pool.free_count()), (2, 0));
+        world.get_component_mut::<Bullet>(first).unwrap().0 = 7;
+
+        assert!(pool.release(&mut world, first));
+        assert!(!pool.release(&mut world, first));
+        assert!(!world.is_enabled(first));
+        assert_eq!(pool.acquire(&mut world), Some(first));
+        assert_eq!(world.get_component::<Bullet>(first), Some(&Bullet(7)));
+        assert!(pool.is_active(second));
+        assert_eq!(world.iter_entities().count(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "pool exhausted")]
+    fn exhausted_pool_panics_under_the_panic_policy() {
+        let mut world = World::new();
+        let mut pool = Pool::<Bullet>::new(&mut world, 1).with_policy(PoolPolicy::Panic);
+        pool.acquire(&mut world);
+        pool.acquire(&mut world);
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SystemTiming {
+    calls: u32,
+    total: Duration,
+}
+
+impl SystemTiming {
+    pub fn calls(&self) -> u32 {
+        self.calls
+    }
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+    pub fn average(&self) -> Duration {
+        self.total / self.calls.max(1)
+    }
+}
+
+/// Wall-clock time spent in each system, keyed by label. Filled in by `App`
+/// once `enable_profiling(true)` is set; displays as a report sorted by
+/// total time, slowest first.
+#[derive(Debug, Default)]
+pub struct SystemProfile {
+    timings: HashMap<&'static str, SystemTiming>,
+}
+
+impl SystemProfile {
+    pub fn get(&self, label: &str) -> Option<&SystemTiming> {
+        self.timings.get(label)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &SystemTiming)> + '_ {
+        self.timings.iter().map(|(&label, timing)| (label, timing))
+    }
+    pub fn clear(&mut self) {
+        self.timings.clear();
+    }
+    pub(crate) fn record(&mut self, label: &'static str, duration: Duration) {
+        let timing = self.timings.entry(label).or_default();
+        timing.calls += 1;
+        timing.total += duration;
+    }
+}
+
+impl fmt::Display for SystemProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut timings: Vec<_> = self.iter().collect();
+        timings.sort_by(|a, b| b.1.total.cmp(&a.1.total).then(a.0.cmp(b.0)));
+        for (label, timing) in timings {
+            writeln!(
+                f,
+                "{label}: {} calls, {:?} total, {:?} average",
+                timing.calls,
+                timing.total,
+                timing.average()
+            )?;
+        }
+        Ok(())
+    }
+}
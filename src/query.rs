@@ -0,0 +1,1040 @@
+use crate::component::{assert_mutable, Component};
+use crate::storage::ComponentStorage;
+use crate::world::{EntityId, World};
+use std::any::{type_name, TypeId};
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Sum;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+pub trait ComponentCombination {
+    type Item<'w>;
+    /// Whether matching depends only on which components an entity has.
+    /// Change filters don't, since they also depend on the current tick.
+    const STRUCTURAL: bool = true;
+    fn filter(world: &World, id: EntityId) -> Option<Self::Item<'_>>;
+    /// Whether `filter` would match, without fetching (or marking changed)
+    /// anything.
+    fn matches(world: &World, id: EntityId) -> bool {
+        Self::filter(world, id).is_some()
+    }
+    /// Entities worth checking, when the combination can narrow them down
+    /// more cheaply than scanning every entity.
+    fn candidates(_world: &World) -> Option<Vec<EntityId>> {
+        None
+    }
+    /// Records which component types `filter` reads or writes. Filters that
+    /// only look at presence or change ticks record nothing.
+    fn access(_access: &mut QueryAccess) {}
+}
+
+/// The component types a query reads and writes.
+///
+/// A query may not touch a type it writes in any other way: `(&mut A, &A)`
+/// and `(&mut A, &mut A)` would hand out aliasing references to the same
+/// component, so creating such a query panics. Different types never
+/// conflict, and `With`/`Without`/`Changed` don't count as access.
+#[derive(Debug, Default, Clone)]
+pub struct QueryAccess {
+    reads: Vec<TypeId>,
+    writes: Vec<TypeId>,
+    names: Vec<(TypeId, &'static str)>,
+}
+
+impl QueryAccess {
+    pub fn reads(&self) -> &[TypeId] {
+        &self.reads
+    }
+    pub fn writes(&self) -> &[TypeId] {
+        &self.writes
+    }
+    pub fn add_read<T: 'static>(&mut self) {
+        let type_id = TypeId::of::<T>();
+        if self.writes.contains(&type_id) {
+            Self::conflict::<T>();
+        }
+        if !self.reads.contains(&type_id) {
+            self.reads.push(type_id);
+            self.names.push((type_id, type_name::<T>()));
+        }
+    }
+    pub fn add_write<T: 'static>(&mut self) {
+        let type_id = TypeId::of::<T>();
+        if self.writes.contains(&type_id) || self.reads.contains(&type_id) {
+            Self::conflict::<T>();
+        }
+        self.writes.push(type_id);
+        self.names.push((type_id, type_name::<T>()));
+    }
+    /// The type name recorded for `type_id`, if it's accessed at all.
+    pub(crate) fn name_of(&self, type_id: TypeId) -> Option<&'static str> {
+        self.names
+            .iter()
+            .find(|(id, _)| *id == type_id)
+            .map(|&(_, name)| name)
+    }
+    /// Panics if `Q` breaks the aliasing rule.
+    pub(crate) fn check<Q: ComponentCombination>() {
+        Q::access(&mut QueryAccess::default());
+    }
+    /// The first accessed type the world has no storage for, i.e. one that
+    /// was never inserted or registered.
+    fn unknown_in(&self, world: &World) -> Option<&'static str> {
+        self.names
+            .iter()
+            .find(|(type_id, _)| world.storage_by_type_id(*type_id).is_none())
+            .map(|&(_, name)| name)
+    }
+    fn conflict<T>() -> ! {
+        panic!(
+            "query accesses `{}` mutably while also accessing it elsewhere",
+            type_name::<T>()
+        )
+    }
+}
+
+/// Combinations that never hand out `&mut`, so any number of their items
+/// can be alive at once.
+///
+/// # Safety
+/// `filter` must not return mutable references.
+pub unsafe trait ReadOnlyComponentCombination: ComponentCombination {}
+
+impl<T: Component> ComponentCombination for &T {
+    type Item<'w> = &'w T;
+    fn filter(world: &World, id: EntityId) -> Option<Self::Item<'_>> {
+        world.storage::<T>()?.get(id)
+    }
+    fn access(access: &mut QueryAccess) {
+        access.add_read::<T>();
+    }
+}
+
+unsafe impl<T: Component> ReadOnlyComponentCombination for &T {}
+
+impl<T: Component> ComponentCombination for &mut T {
+    type Item<'w> = &'w mut T;
+    fn filter(world: &World, id: EntityId) -> Option<Self::Item<'_>> {
+        const { assert_mutable::<T>() };
+        let storage = world.storage::<T>()?;
+        // SAFETY: mutable combinations are only reachable through
+        // `Query`'s `&mut self` methods and the consuming iterator, which
+        // visit each entity at most once per borrow of the query, and
+        // `QueryAccess` rejects any other member touching `T`.
+        let component = unsafe { storage.get_unchecked_mut(id)? };
+        storage.mark_changed(id, world.change_tick());
+        Some(component)
+    }
+    fn matches(world: &World, id: EntityId) -> bool {
+        world.has_component::<T>(id)
+    }
+    fn access(access: &mut QueryAccess) {
+        access.add_write::<T>();
+    }
+}
+
+pub struct With<T>(PhantomData<T>);
+
+impl<T: Component> ComponentCombination for With<T> {
+    type Item<'w> = ();
+    fn filter(world: &World, id: EntityId) -> Option<Self::Item<'_>> {
+        world.storage::<T>()?.contains(id).then_some(())
+    }
+}
+
+unsafe impl<T: Component> ReadOnlyComponentCombination for With<T> {}
+
+pub struct Without<T>(PhantomData<T>);
+
+impl<T: Component> ComponentCombination for Without<T> {
+    type Item<'w> = ();
+    fn filter(world: &World, id: EntityId) -> Option<Self::Item<'_>> {
+        let present = world
+            .storage::<T>()
+            .is_some_and(|storage| storage.contains(id));
+        (!present).then_some(())
+    }
+}
+
+unsafe impl<T: Component> ReadOnlyComponentCombination for Without<T> {}
+
+/// Matches when any of the tuple's members matches; yields nothing.
+pub struct Or<T>(PhantomData<T>);
+
+/// Matches entities whose `T` was inserted or mutably fetched during the
+/// current tick. Inside an `App` system it matches changes made since that
+/// system last ran instead, so every system sees each change once, even if
+/// it was skipped for a few frames. Ticks only advance between frames, so
+/// a change made after a system already ran this frame isn't reported to
+/// it.
+///
+/// Yields a `Changed<T>` rather than `()` so that `Vec<Changed<T>>` works as
+/// a system parameter.
+pub struct Changed<T>(PhantomData<T>);
+
+impl<T: Component> ComponentCombination for Changed<T> {
+    type Item<'w> = Changed<T>;
+    const STRUCTURAL: bool = false;
+    fn filter(world: &World, id: EntityId) -> Option<Self::Item<'_>> {
+        world
+            .storage::<T>()?
+            .is_changed(id, world.changed_since())
+            .then_some(Changed(PhantomData))
+    }
+    fn candidates(world: &World) -> Option<Vec<EntityId>> {
+        Some(
+            world
+                .storage::<T>()
+                .map(|storage| storage.changed_entities(world.changed_since()))
+                .unwrap_or_default(),
+        )
+    }
+}
+
+unsafe impl<T: Component> ReadOnlyComponentCombination for Changed<T> {}
+
+/// Like `&T`, also telling whether the component changed, in the sense of
+/// `Changed<T>`: `query::<Ref<MoveTo>>()` yields every `MoveTo`, and
+/// `is_changed` picks out the ones to react to.
+pub struct Ref<'w, T> {
+    value: &'w T,
+    changed: bool,
+}
+
+impl<'w, T> Ref<'w, T> {
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+    pub fn into_inner(self) -> &'w T {
+        self.value
+    }
+}
+
+impl<T> Deref for Ref<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T: Component> ComponentCombination for Ref<'_, T> {
+    type Item<'w> = Ref<'w, T>;
+    fn filter(world: &World, id: EntityId) -> Option<Self::Item<'_>> {
+        let storage = world.storage::<T>()?;
+        Some(Ref {
+            value: storage.get(id)?,
+            changed: storage.is_changed(id, world.changed_since()),
+        })
+    }
+    fn matches(world: &World, id: EntityId) -> bool {
+        world.has_component::<T>(id)
+    }
+    fn access(access: &mut QueryAccess) {
+        access.add_read::<T>();
+    }
+}
+
+unsafe impl<T: Component> ReadOnlyComponentCombination for Ref<'_, T> {}
+
+/// Like `&mut T`, except the component only counts as changed once it's
+/// actually written through `DerefMut`, so a system can look at every
+/// component and change just a few without the rest showing up in
+/// `Changed<T>`.
+pub struct Mut<'w, T> {
+    value: &'w mut T,
+    changed: bool,
+    storage: &'w ComponentStorage<T>,
+    id: EntityId,
+    tick: u64,
+}
+
+impl<T: Component> Mut<'_, T> {
+    /// Whether the component changed, in the sense of `Changed<T>`,
+    /// including through this `Mut`.
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T: Component> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        if !self.changed {
+            self.storage.mark_changed(self.id, self.tick);
+            self.changed = true;
+        }
+        self.value
+    }
+}
+
+impl<T: Component> ComponentCombination for Mut<'_, T> {
+    type Item<'w> = Mut<'w, T>;
+    fn filter(world: &World, id: EntityId) -> Option<Self::Item<'_>> {
+        const { assert_mutable::<T>() };
+        let storage = world.storage::<T>()?;
+        // SAFETY: as for `&mut T`.
+        let value = unsafe { storage.get_unchecked_mut(id)? };
+        Some(Mut {
+            value,
+            changed: storage.is_changed(id, world.changed_since()),
+            storage,
+            id,
+            tick: world.change_tick(),
+        })
+    }
+    fn matches(world: &World, id: EntityId) -> bool {
+        world.has_component::<T>(id)
+    }
+    fn access(access: &mut QueryAccess) {
+        access.add_write::<T>();
+    }
+}
+
+/// Matches entities matching both `D` and `F`, yielding only `D`'s item, so
+/// filters like `With` don't leave `()` slots in it. `F` is only checked
+/// with `matches`, never fetched. Built by `World::query_filtered`.
+pub struct Filtered<D, F>(PhantomData<(D, F)>);
+
+impl<D: ComponentCombination, F: ComponentCombination> ComponentCombination for Filtered<D, F> {
+    type Item<'w> = D::Item<'w>;
+    const STRUCTURAL: bool = D::STRUCTURAL && F::STRUCTURAL;
+    fn filter(world: &World, id: EntityId) -> Option<Self::Item<'_>> {
+        if !F::matches(world, id) {
+            return None;
+        }
+        D::filter(world, id)
+    }
+    fn matches(world: &World, id: EntityId) -> bool {
+        F::matches(world, id) && D::matches(world, id)
+    }
+    fn candidates(world: &World) -> Option<Vec<EntityId>> {
+        [D::candidates(world), F::candidates(world)]
+            .into_iter()
+            .flatten()
+            .min_by_key(Vec::len)
+    }
+    fn access(access: &mut QueryAccess) {
+        D::access(access);
+        F::access(access);
+    }
+}
+
+// `F` only ever gets `matches`, so it hands out no references either way.
+unsafe impl<D: ReadOnlyComponentCombination, F: ComponentCombination> ReadOnlyComponentCombination
+    for Filtered<D, F>
+{
+}
+
+/// Every match of the read-only query `T`. Fails if `T` reads a component
+/// type the world has never stored, since such a query can't match
+/// anything and is almost always a mistake; `query_unchecked` returns an
+/// empty `Vec` instead.
+pub fn get_components<T: ReadOnlyComponentCombination>(
+    world: &World,
+) -> Result<Vec<T::Item<'_>>, QueryError> {
+    fetch_components::<T>(world)
+}
+
+/// Like `get_components`, for queries with a `&mut` member.
+pub fn get_components_mut<T: ComponentCombination>(
+    world: &mut World,
+) -> Result<Vec<T::Item<'_>>, QueryError> {
+    fetch_components::<T>(world)
+}
+
+/// Like `get_components`, without checking that the accessed component
+/// types exist.
+pub fn query_unchecked<T: ReadOnlyComponentCombination>(world: &World) -> Vec<T::Item<'_>> {
+    world.query::<T>().into_iter().collect()
+}
+
+/// `get_components` for any query, for system parameters whose aliasing
+/// `SystemAccess` already rules out.
+pub(crate) fn fetch_components<T: ComponentCombination>(
+    world: &World,
+) -> Result<Vec<T::Item<'_>>, QueryError> {
+    let mut access = QueryAccess::default();
+    T::access(&mut access);
+    match access.unknown_in(world) {
+        Some(name) => Err(QueryError::UnknownComponent(name)),
+        None => Ok(Query::<T>::new(world, false).into_iter().collect()),
+    }
+}
+
+pub struct Query<'w, Q: ComponentCombination> {
+    world: &'w World,
+    include_disabled: bool,
+    marker: PhantomData<Q>,
+}
+
+/// Creating a query panics if it breaks the aliasing rule described on
+/// `QueryAccess`.
+///
+/// Read-only queries can be iterated through `&self` as often as you like.
+/// Queries with a `&mut` member go through `iter_mut`, `get_mut` and
+/// `single_mut` instead, whose items borrow the query mutably, so no two
+/// `&mut` to the same component can coexist.
+impl<'w, Q: ComponentCombination> Query<'w, Q> {
+    pub(crate) fn new(world: &'w World, include_disabled: bool) -> Self {
+        QueryAccess::check::<Q>();
+        Self {
+            world,
+            include_disabled,
+            marker: PhantomData,
+        }
+    }
+    pub fn iter_mut(&mut self) -> QueryIter<'_, Q> {
+        QueryIter::new(self.world, self.include_disabled)
+    }
+    pub fn get_mut(&mut self, id: EntityId) -> Option<Q::Item<'_>> {
+        Self::get_in(self.world, self.include_disabled, id)
+    }
+    pub fn single_mut(&mut self) -> Result<Q::Item<'_>, QuerySingleError> {
+        Self::single_in(QueryIter::new(self.world, self.include_disabled))
+    }
+    /// How many entities match, checked with `matches` so nothing is
+    /// collected, fetched or marked changed.
+    pub fn count(&self) -> usize {
+        let counts = |&id: &EntityId| {
+            self.world.is_queryable(id, self.include_disabled) && Q::matches(self.world, id)
+        };
+        match Q::candidates(self.world) {
+            Some(candidates) => candidates.into_iter().filter(counts).count(),
+            None => self.world.iter_entities().filter(counts).count(),
+        }
+    }
+    /// Projects each match through `f`, e.g. to collect one field of a large
+    /// component without keeping the references around.
+    pub fn map<U, F: FnMut(Q::Item<'w>) -> U>(self, f: F) -> std::iter::Map<QueryIter<'w, Q>, F> {
+        self.into_iter().map(f)
+    }
+    /// Each match paired with the entity it came from, e.g. to remember
+    /// which entity to despawn without adding `EntityId` to the query.
+    pub fn with_entities(self) -> WithEntities<'w, Q> {
+        WithEntities(self.into_iter())
+    }
+    /// Adds up `f` over every match without collecting them, e.g. the
+    /// centroid of all move targets:
+    ///
+    /// ```ignore
+    /// let count = world.query::<&MoveTo>().count() as f32;
+    /// let x = world.query::<&MoveTo>().sum_by(|target| target.x) / count;
+    /// let y = world.query::<&MoveTo>().sum_by(|target| target.y) / count;
+    /// ```
+    pub fn sum_by<S: Sum, F: FnMut(Q::Item<'w>) -> S>(self, f: F) -> S {
+        self.into_iter().map(f).sum()
+    }
+    /// The match with the smallest key, the first one on ties; `None` if
+    /// nothing matches. Keys only need `PartialOrd`, so floats work, though
+    /// a NaN key makes the result arbitrary.
+    pub fn min_by_key<K: PartialOrd, F>(self, key: F) -> Option<Q::Item<'w>>
+    where
+        F: FnMut(&Q::Item<'w>) -> K,
+    {
+        self.best_by_key(key, |new, best| new < best)
+    }
+    /// Like `min_by_key`, for the largest key.
+    pub fn max_by_key<K: PartialOrd, F>(self, key: F) -> Option<Q::Item<'w>>
+    where
+        F: FnMut(&Q::Item<'w>) -> K,
+    {
+        self.best_by_key(key, |new, best| new > best)
+    }
+    fn best_by_key<K, F>(self, mut key: F, beats: fn(&K, &K) -> bool) -> Option<Q::Item<'w>>
+    where
+        F: FnMut(&Q::Item<'w>) -> K,
+    {
+        let mut best: Option<(K, Q::Item<'w>)> = None;
+        for item in self {
+            let item_key = key(&item);
+            if best
+                .as_ref()
+                .is_none_or(|(best_key, _)| beats(&item_key, best_key))
+            {
+                best = Some((item_key, item));
+            }
+        }
+        best.map(|(_, item)| item)
+    }
+    /// All matches ordered by `compare`, e.g. by a z-index for rendering.
+    /// Sorting needs every item at once, so this collects them into a `Vec`.
+    pub fn sorted_by<F>(self, mut compare: F) -> std::vec::IntoIter<Q::Item<'w>>
+    where
+        F: FnMut(&Q::Item<'w>, &Q::Item<'w>) -> Ordering,
+    {
+        let mut items: Vec<_> = self.into_iter().collect();
+        items.sort_by(|a, b| compare(a, b));
+        items.into_iter()
+    }
+    /// Like `sorted_by`, comparing the keys `key` extracts.
+    pub fn sorted_by_key<K: Ord, F>(self, mut key: F) -> std::vec::IntoIter<Q::Item<'w>>
+    where
+        F: FnMut(&Q::Item<'w>) -> K,
+    {
+        self.sorted_by(|a, b| key(a).cmp(&key(b)))
+    }
+    /// Splits the matches into at most `n` chunks whose lengths differ by
+    /// at most one, to hand out to worker threads. No chunk is empty, so
+    /// fewer than `n` matches give fewer chunks. Every match lands in
+    /// exactly one chunk, which is what makes this sound for `&mut`
+    /// queries. Panics if `n` is 0.
+    ///
+    /// ```ignore
+    /// std::thread::scope(|scope| {
+    ///     for chunk in world.query_mut::<&mut MoveTo>().into_chunks(4) {
+    ///         scope.spawn(move || chunk.into_iter().for_each(|target| target.x += 1.0));
+    ///     }
+    /// });
+    /// ```
+    pub fn into_chunks(self, n: usize) -> Vec<Vec<Q::Item<'w>>> {
+        assert!(n > 0, "chunk count must be non-zero");
+        let mut items = self.into_iter().collect::<Vec<_>>().into_iter();
+        let (base, extra) = (items.len() / n, items.len() % n);
+        (0..n)
+            .map(|chunk| {
+                items
+                    .by_ref()
+                    .take(base + usize::from(chunk < extra))
+                    .collect()
+            })
+            .filter(|chunk: &Vec<_>| !chunk.is_empty())
+            .collect()
+    }
+    fn get_in(world: &World, include_disabled: bool, id: EntityId) -> Option<Q::Item<'_>> {
+        if !world.is_queryable(id, include_disabled) {
+            return None;
+        }
+        Q::filter(world, id)
+    }
+    fn single_in(mut iter: QueryIter<'_, Q>) -> Result<Q::Item<'_>, QuerySingleError> {
+        let item = iter.next().ok_or(QuerySingleError::NoEntities)?;
+        match iter.count() {
+            0 => Ok(item),
+            rest => Err(QuerySingleError::MultipleEntities(rest + 1)),
+        }
+    }
+}
+
+impl<'w, Q: ReadOnlyComponentCombination> Query<'w, Q> {
+    pub fn iter(&self) -> QueryIter<'w, Q> {
+        QueryIter::new(self.world, self.include_disabled)
+    }
+    /// The query's item for one entity, or `None` if it's dead or doesn't
+    /// match.
+    pub fn get(&self, id: EntityId) -> Option<Q::Item<'w>> {
+        Self::get_in(self.world, self.include_disabled, id)
+    }
+    /// The only match, or an error saying whether there were none or how
+    /// many there were.
+    pub fn single(&self) -> Result<Q::Item<'w>, QuerySingleError> {
+        Self::single_in(self.iter())
+    }
+    /// Every unordered set of `N` distinct matches, each yielded once, e.g.
+    /// all pairs for a broad-phase collision check.
+    pub fn iter_combinations<const N: usize>(&self) -> QueryCombinations<'w, Q, N>
+    where
+        Q::Item<'w>: Clone,
+    {
+        let items: Vec<_> = self.iter().collect();
+        QueryCombinations {
+            done: N == 0 || N > items.len(),
+            indices: std::array::from_fn(|index| index),
+            items,
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<Q: ComponentCombination> Query<'_, Q> {
+    /// Runs `f` on every match across rayon's thread pool. Matches are
+    /// gathered on the calling thread first, so only the items themselves
+    /// cross threads and each `&mut` goes to exactly one invocation.
+    pub fn par_for_each<F>(&mut self, f: F)
+    where
+        F: Fn(Q::Item<'_>) + Send + Sync,
+        for<'a> Q::Item<'a>: Send,
+    {
+        use rayon::prelude::*;
+
+        let items: Vec<_> = self.iter_mut().collect();
+        items.into_par_iter().for_each(f);
+    }
+}
+
+impl<'w, Q: ComponentCombination> IntoIterator for Query<'w, Q> {
+    type Item = Q::Item<'w>;
+    type IntoIter = QueryIter<'w, Q>;
+    fn into_iter(self) -> Self::IntoIter {
+        QueryIter::new(self.world, self.include_disabled)
+    }
+}
+
+impl<'w, Q: ReadOnlyComponentCombination> IntoIterator for &Query<'w, Q> {
+    type Item = Q::Item<'w>;
+    type IntoIter = QueryIter<'w, Q>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, Q: ComponentCombination> IntoIterator for &'a mut Query<'_, Q> {
+    type Item = Q::Item<'a>;
+    type IntoIter = QueryIter<'a, Q>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Checks each entity is alive (and enabled, unless the query came from
+/// `query_including_disabled`) right before filtering it, so an id that
+/// went stale after a candidate list was built never produces an item.
+/// Despawns queued through `Commands` only count once they're applied.
+pub struct QueryIter<'w, Q: ComponentCombination> {
+    world: &'w World,
+    include_disabled: bool,
+    candidates: Option<std::vec::IntoIter<EntityId>>,
+    next_slot: usize,
+    marker: PhantomData<Q>,
+}
+
+impl<'w, Q: ComponentCombination> QueryIter<'w, Q> {
+    fn new(world: &'w World, include_disabled: bool) -> Self {
+        Self {
+            world,
+            include_disabled,
+            candidates: Q::candidates(world).map(Vec::into_iter),
+            next_slot: 0,
+            marker: PhantomData,
+        }
+    }
+    /// Like `next`, also returning the matched entity.
+    fn next_with_id(&mut self) -> Option<(EntityId, Q::Item<'w>)> {
+        loop {
+            let id = match &mut self.candidates {
+                Some(candidates) => candidates.next()?,
+                None => {
+                    if self.next_slot >= self.world.entity_bound() {
+                        return None;
+                    }
+                    self.next_slot += 1;
+                    match self.world.entity_at(self.next_slot - 1) {
+                        Some(id) => id,
+                        None => continue,
+                    }
+                }
+            };
+            if !self.world.is_queryable(id, self.include_disabled) {
+                continue;
+            }
+            if let Some(item) = Q::filter(self.world, id) {
+                return Some((id, item));
+            }
+        }
+    }
+}
+
+impl<'w, Q: ComponentCombination> Iterator for QueryIter<'w, Q> {
+    type Item = Q::Item<'w>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_id().map(|(_, item)| item)
+    }
+}
+
+/// Iterator returned by `Query::with_entities`.
+pub struct WithEntities<'w, Q: ComponentCombination>(QueryIter<'w, Q>);
+
+impl<'w, Q: ComponentCombination> Iterator for WithEntities<'w, Q> {
+    type Item = (EntityId, Q::Item<'w>);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_with_id()
+    }
+}
+
+impl World {
+    /// The query's item for one entity, looked up directly in each member's
+    /// storage rather than by iterating, e.g. to update the player every
+    /// frame. `None` if it's dead, disabled or doesn't match.
+    pub fn query_one_mut<Q: ComponentCombination>(&mut self, id: EntityId) -> Option<Q::Item<'_>> {
+        QueryAccess::check::<Q>();
+        Query::<Q>::get_in(self, false, id)
+    }
+    /// Every match of `A` paired with every match of `B`, each with its
+    /// entity, e.g. to pair each enemy with each player. Unlike a tuple
+    /// query the two sides come from different entities (an entity matching
+    /// both is paired with itself too). Yields n·m pairs, so it gets
+    /// expensive quickly.
+    pub fn query_pair<'w, A, B>(
+        &'w self,
+    ) -> impl Iterator<Item = ((EntityId, A::Item<'w>), (EntityId, B::Item<'w>))> + 'w
+    where
+        A: ReadOnlyComponentCombination + 'w,
+        B: ReadOnlyComponentCombination + 'w,
+        A::Item<'w>: Clone,
+        B::Item<'w>: Clone,
+    {
+        let left: Vec<_> = self.query::<A>().with_entities().collect();
+        let right: Vec<_> = self.query::<B>().with_entities().collect();
+        left.into_iter()
+            .flat_map(move |a| right.clone().into_iter().map(move |b| (a.clone(), b)))
+    }
+}
+
+pub struct QueryCombinations<'w, Q: ComponentCombination, const N: usize> {
+    items: Vec<Q::Item<'w>>,
+    indices: [usize; N],
+    done: bool,
+}
+
+impl<'w, Q: ComponentCombination, const N: usize> Iterator for QueryCombinations<'w, Q, N>
+where
+    Q::Item<'w>: Clone,
+{
+    type Item = [Q::Item<'w>; N];
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let combination = self.indices.map(|index| self.items[index].clone());
+        // Advance the rightmost index that still has room, then pack the
+        // ones after it right behind it.
+        let len = self.items.len();
+        match (0..N)
+            .rev()
+            .find(|&slot| self.indices[slot] < len - N + slot)
+        {
+            Some(slot) => {
+                self.indices[slot] += 1;
+                for next in slot + 1..N {
+                    self.indices[next] = self.indices[next - 1] + 1;
+                }
+            }
+            None => self.done = true,
+        }
+        Some(combination)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuerySingleError {
+    NoEntities,
+    MultipleEntities(usize),
+}
+
+impl fmt::Display for QuerySingleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuerySingleError::NoEntities => write!(f, "query matched no entities"),
+            QuerySingleError::MultipleEntities(count) => {
+                write!(f, "query matched {count} entities, expected one")
+            }
+        }
+    }
+}
+
+impl std::error::Error for QuerySingleError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryError {
+    /// No component of this type was ever inserted or registered.
+    UnknownComponent(&'static str),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::UnknownComponent(name) => {
+                write!(
+                    f,
+                    "query accesses `{name}`, which was never inserted or registered"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+macro_rules! impl_component_combination {
+    ($($member:ident),+) => {
+        impl<$($member: ComponentCombination),+> ComponentCombination for ($($member,)+) {
+            type Item<'w> = ($($member::Item<'w>,)+);
+            const STRUCTURAL: bool = $($member::STRUCTURAL)&&+;
+            fn filter(world: &World, id: EntityId) -> Option<Self::Item<'_>> {
+                Some(($($member::filter(world, id)?,)+))
+            }
+            fn matches(world: &World, id: EntityId) -> bool {
+                $($member::matches(world, id))&&+
+            }
+            fn candidates(world: &World) -> Option<Vec<EntityId>> {
+                [$($member::candidates(world)),+]
+                    .into_iter()
+                    .flatten()
+                    .min_by_key(Vec::len)
+            }
+            fn access(access: &mut QueryAccess) {
+                $($member::access(access);)+
+            }
+        }
+
+        impl<$($member: ComponentCombination),+> ComponentCombination for Or<($($member,)+)> {
+            type Item<'w> = ();
+            const STRUCTURAL: bool = $($member::STRUCTURAL)&&+;
+            fn filter(world: &World, id: EntityId) -> Option<Self::Item<'_>> {
+                Self::matches(world, id).then_some(())
+            }
+            fn matches(world: &World, id: EntityId) -> bool {
+                $($member::matches(world, id))||+
+            }
+            fn access(access: &mut QueryAccess) {
+                $($member::access(access);)+
+            }
+        }
+
+        unsafe impl<$($member: ReadOnlyComponentCombination),+> ReadOnlyComponentCombination
+            for ($($member,)+)
+        {
+        }
+
+        unsafe impl<$($member: ReadOnlyComponentCombination),+> ReadOnlyComponentCombination
+            for Or<($($member,)+)>
+        {
+        }
+    };
+}
+
+impl_component_combination!(A);
+impl_component_combination!(A, B);
+impl_component_combination!(A, B, C);
+impl_component_combination!(A, B, C, D);
+impl_component_combination!(A, B, C, D, E);
+impl_component_combination!(A, B, C, D, E, G);
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        get_components, get_components_mut, Changed, Component, ComponentCombination, EntityId,
+        Mut, Or, ReadOnlyComponentCombination, Ref, With, Without, World,
+    };
+    use std::cell::Cell;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    impl Component for Position {}
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+    impl Component for Velocity {}
+
+    #[test]
+    fn single_member_tuple_matches_plain_query() {
+        let mut world = World::new();
+        world.spawn().insert(Position(1));
+        world.spawn().insert(Velocity(2));
+        world.spawn().insert(Position(3)).insert(Velocity(4));
+        let plain = get_components::<&Position>(&world).unwrap();
+        let tuple = get_components::<(&Position,)>(&world).unwrap();
+        assert_eq!(
+            tuple
+                .into_iter()
+                .map(|(position,)| position)
+                .collect::<Vec<_>>(),
+            plain
+        );
+    }
+
+    thread_local! {
+        static PROBED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Matches everything, counting the entities it's asked about.
+    struct Probe;
+
+    impl ComponentCombination for Probe {
+        type Item<'w> = ();
+        fn filter(_world: &World, _id: EntityId) -> Option<Self::Item<'_>> {
+            PROBED.with(|probed| probed.set(probed.get() + 1));
+            Some(())
+        }
+    }
+
+    unsafe impl ReadOnlyComponentCombination for Probe {}
+
+    #[test]
+    fn changed_visits_only_changed_entities() {
+        let mut world = World::new();
+        let ids = world.spawn_batch((0..100_000).map(Position));
+        world.advance_tick();
+        world.get_component_mut::<Position>(ids[10]).unwrap().0 += 1;
+        world.get_component_mut::<Position>(ids[99_000]).unwrap().0 += 1;
+
+        PROBED.with(|probed| probed.set(0));
+        let changed = world.query::<(Probe, Changed<Position>)>().iter().count();
+        assert_eq!(changed, 2);
+        assert_eq!(PROBED.with(Cell::get), 2);
+    }
+
+    #[test]
+    fn or_matches_either_member() {
+        let mut world = World::new();
+        let both = world.spawn().insert(Position(1)).insert(Velocity(1)).id();
+        let moving = world.spawn().insert(Velocity(2)).id();
+        let placed = world.spawn().insert(Position(3)).id();
+        world.spawn();
+        let ids: Vec<_> = world
+            .query::<Or<(With<Position>, With<Velocity>)>>()
+            .with_entities()
+            .map(|(id, ())| id)
+            .collect();
+        assert_eq!(ids, vec![both, moving, placed]);
+        let ids: Vec<_> = world
+            .query::<(Or<(With<Position>, With<Velocity>)>, Without<Position>)>()
+            .with_entities()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, vec![moving]);
+    }
+
+    #[test]
+    fn mutable_query_writes_show_up_in_later_reads() {
+        let mut world = World::new();
+        world.spawn().insert(Position(1));
+        world.spawn().insert(Position(2));
+        for position in world.query_mut::<&mut Position>() {
+            position.0 *= 10;
+        }
+        let positions: Vec<_> = world.query::<&Position>().iter().collect();
+        assert_eq!(positions, vec![&Position(10), &Position(20)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "mutably while also accessing it elsewhere")]
+    fn mutable_and_shared_access_to_one_type_is_rejected() {
+        let mut world = World::new();
+        world.spawn().insert(Position(1));
+        let _ = world.query_mut::<(&mut Position, &Position)>();
+    }
+
+    #[test]
+    #[should_panic(expected = "mutably while also accessing it elsewhere")]
+    fn two_mutable_accesses_to_one_type_are_rejected() {
+        let mut world = World::new();
+        world.spawn().insert(Position(1));
+        let _ = get_components_mut::<(&mut Position, &mut Position)>(&mut world);
+    }
+
+    #[test]
+    fn despawned_entities_drop_out_of_queries_immediately() {
+        let mut world = World::new();
+        let kept = world.spawn().insert(Position(1)).id();
+        let gone = world.spawn().insert(Position(2)).id();
+        world.despawn(gone);
+        let ids: Vec<_> = world
+            .query::<&Position>()
+            .with_entities()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, vec![kept]);
+
+        world.commands().entity(kept).despawn();
+        world.apply_commands();
+        assert_eq!(world.query::<&Position>().iter().count(), 0);
+    }
+
+    #[test]
+    fn two_mutable_members_write_through() {
+        let mut world = World::new();
+        let first = world.spawn().insert(Position(1)).insert(Velocity(10)).id();
+        let second = world.spawn().insert(Position(2)).insert(Velocity(20)).id();
+        world.spawn().insert(Position(3));
+
+        let items = get_components_mut::<(&mut Position, &mut Velocity)>(&mut world).unwrap();
+        assert_eq!(items.len(), 2);
+        for (position, velocity) in items {
+            position.0 += velocity.0;
+            velocity.0 = -velocity.0;
+        }
+
+        assert_eq!(world.get_component::<Position>(first), Some(&Position(11)));
+        assert_eq!(world.get_component::<Velocity>(first), Some(&Velocity(-10)));
+        assert_eq!(world.get_component::<Position>(second), Some(&Position(22)));
+        assert_eq!(
+            world.get_component::<Velocity>(second),
+            Some(&Velocity(-20))
+        );
+    }
+
+    #[test]
+    fn mut_only_marks_changed_when_written() {
+        let mut world = World::new();
+        let read = world.spawn().insert(Position(1)).id();
+        let written = world.spawn().insert(Position(2)).id();
+        world.advance_tick();
+        for (id, mut position) in world.query_mut::<Mut<Position>>().with_entities() {
+            assert!(!position.is_changed());
+            if id == written {
+                position.0 += 1;
+                assert!(position.is_changed());
+            } else {
+                assert_eq!(position.0, 1);
+            }
+        }
+        let changed: Vec<_> = world
+            .query::<Changed<Position>>()
+            .with_entities()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(changed, vec![written]);
+        let flags: Vec<_> = world
+            .query::<Ref<Position>>()
+            .with_entities()
+            .map(|(id, position)| (id, position.is_changed()))
+            .collect();
+        assert_eq!(flags, vec![(read, false), (written, true)]);
+    }
+
+    #[test]
+    fn into_chunks_spreads_the_remainder_over_the_first_chunks() {
+        let mut world = World::new();
+        world.spawn_batch((0..10).map(Position));
+        let chunks = world.query::<&Position>().into_chunks(4);
+        let sizes: Vec<_> = chunks.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![3, 3, 2, 2]);
+        let flattened: Vec<_> = chunks
+            .into_iter()
+            .flatten()
+            .map(|position| position.0)
+            .collect();
+        assert_eq!(flattened, (0..10).collect::<Vec<_>>());
+
+        let chunks = world.query::<&Position>().into_chunks(3);
+        assert_eq!(
+            chunks.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![4, 3, 3]
+        );
+        assert_eq!(world.query::<&Position>().into_chunks(20).len(), 10);
+    }
+
+    #[test]
+    fn mutable_chunks_cover_each_entity_once() {
+        let mut world = World::new();
+        world.spawn_batch((0..7).map(Position));
+        for chunk in world.query_mut::<&mut Position>().into_chunks(3) {
+            for position in chunk {
+                position.0 += 100;
+            }
+        }
+        let positions: Vec<_> = world
+            .query::<&Position>()
+            .iter()
+            .map(|position| position.0)
+            .collect();
+        assert_eq!(positions, (100..107).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,123 @@
+use crate::query::{ComponentCombination, QueryAccess, ReadOnlyComponentCombination};
+use crate::world::{EntityId, World};
+use std::marker::PhantomData;
+
+/// A query that remembers which entities matched, so running it again only
+/// visits those instead of scanning every entity. The snapshot is rebuilt
+/// when entities or their component sets change. Queries with change
+/// filters can't be cached and are rebuilt on every run. Like
+/// `World::query`, it skips disabled entities; as with `query_mut`, states
+/// with a `&mut` member come from `query_state_mut` and run through
+/// `iter_mut`.
+pub struct QueryState<Q: ComponentCombination> {
+    ids: Vec<EntityId>,
+    structure_version: u64,
+    marker: PhantomData<Q>,
+}
+
+impl<Q: ComponentCombination> QueryState<Q> {
+    fn new(world: &World) -> Self {
+        QueryAccess::check::<Q>();
+        let mut state = Self {
+            ids: Vec::new(),
+            structure_version: 0,
+            marker: PhantomData,
+        };
+        state.rebuild(world);
+        state
+    }
+    /// Items of the cached matches. Borrows the world mutably, so no other
+    /// access to it can overlap the `&mut` items.
+    pub fn iter_mut<'s>(
+        &'s mut self,
+        world: &'s mut World,
+    ) -> impl Iterator<Item = Q::Item<'s>> + 's {
+        self.items(world)
+    }
+    /// How many entities matched when the snapshot was last rebuilt.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+    fn items<'s>(&'s mut self, world: &'s World) -> impl Iterator<Item = Q::Item<'s>> + 's {
+        if self.is_stale(world) {
+            self.rebuild(world);
+        }
+        self.ids.iter().filter_map(|&id| Q::filter(world, id))
+    }
+    fn is_stale(&self, world: &World) -> bool {
+        !Q::STRUCTURAL || self.structure_version != world.structure_version()
+    }
+    fn rebuild(&mut self, world: &World) {
+        self.ids = match Q::candidates(world) {
+            Some(candidates) => candidates,
+            None => world.iter_entities().collect(),
+        };
+        self.ids
+            .retain(|&id| world.is_enabled(id) && Q::matches(world, id));
+        self.structure_version = world.structure_version();
+    }
+}
+
+impl<Q: ReadOnlyComponentCombination> QueryState<Q> {
+    /// Items of the cached matches. Needs the state mutably only to refresh
+    /// the cache.
+    pub fn iter<'s>(&'s mut self, world: &'s World) -> impl Iterator<Item = Q::Item<'s>> + 's {
+        self.items(world)
+    }
+}
+
+impl World {
+    /// A cached read-only query; see `query_state_mut` for ones with a
+    /// `&mut` member.
+    pub fn query_state<Q: ReadOnlyComponentCombination>(&self) -> QueryState<Q> {
+        QueryState::new(self)
+    }
+    /// Like `query_state`, for any query including ones with a `&mut`
+    /// member.
+    pub fn query_state_mut<Q: ComponentCombination>(&mut self) -> QueryState<Q> {
+        QueryState::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Component, World};
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+    impl Component for Health {}
+
+    #[test]
+    fn iter_mut_writes_are_visible_afterwards() {
+        let mut world = World::new();
+        let first = world.spawn().insert(Health(1)).id();
+        let second = world.spawn().insert(Health(2)).id();
+        let mut state = world.query_state_mut::<&mut Health>();
+        for health in state.iter_mut(&mut world) {
+            health.0 *= 10;
+        }
+        assert_eq!(world.get_component::<Health>(first), Some(&Health(10)));
+        assert_eq!(world.get_component::<Health>(second), Some(&Health(20)));
+    }
+
+    #[test]
+    fn cache_refreshes_after_spawn_and_despawn() {
+        let mut world = World::new();
+        let first = world.spawn().insert(Health(1)).id();
+        let mut state = world.query_state::<&Health>();
+        assert_eq!(state.iter(&world).count(), 1);
+        world.spawn().insert(Health(2));
+        assert_eq!(state.iter(&world).count(), 2);
+        world.despawn(first);
+        let remaining: Vec<_> = state.iter(&world).collect();
+        assert_eq!(remaining, vec![&Health(2)]);
+        let bare = world.spawn().id();
+        world.add_component(bare, Health(3));
+        assert_eq!(state.iter(&world).count(), 2);
+        world.remove_component::<Health>(bare);
+        assert_eq!(state.iter(&world).count(), 1);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::component::Component;
+use crate::snapshot::ComponentSnapshot;
+use crate::world::{EntityId, World};
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+/// Runtime handle for a registered component type, for code that can't name
+/// the type, like an editor or scripting layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ComponentId(usize);
+
+/// What the world knows about a registered component type, enough to work
+/// with its storage without naming `T`.
+pub struct ComponentInfo {
+    id: ComponentId,
+    type_id: TypeId,
+    name: String,
+    key: Option<String>,
+    remove: fn(&mut World, EntityId) -> bool,
+    debug: fn(&World, EntityId) -> Option<String>,
+    clone: Option<fn(&mut World, EntityId, EntityId)>,
+    pub(crate) snapshot: Option<fn(&World) -> ComponentSnapshot>,
+}
+
+impl ComponentInfo {
+    pub fn id(&self) -> ComponentId {
+        self.id
+    }
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// The stable name given to `register_component_with_name`, if any.
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+    /// Removes (and drops) the entity's component, returning whether it had
+    /// one.
+    pub fn remove(&self, world: &mut World, id: EntityId) -> bool {
+        (self.remove)(world, id)
+    }
+    /// The entity's component formatted with `Debug`, if it has one.
+    pub fn debug(&self, world: &World, id: EntityId) -> Option<String> {
+        (self.debug)(world, id)
+    }
+    /// Whether `clone_entity` copies this component and `World::snapshot`
+    /// saves it.
+    pub fn is_cloneable(&self) -> bool {
+        self.clone.is_some()
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ComponentRegistry {
+    infos: Vec<ComponentInfo>,
+    indices: HashMap<TypeId, usize>,
+}
+
+impl ComponentRegistry {
+    pub(crate) fn get(&self, type_id: TypeId) -> Option<&ComponentInfo> {
+        self.infos.get(*self.indices.get(&type_id)?)
+    }
+    pub(crate) fn get_by_id(&self, id: ComponentId) -> Option<&ComponentInfo> {
+        self.infos.get(id.0)
+    }
+    pub(crate) fn iter(&self) -> std::slice::Iter<'_, ComponentInfo> {
+        self.infos.iter()
+    }
+}
+
+fn remove_component<T: Component>(world: &mut World, id: EntityId) -> bool {
+    world.remove_component::<T>(id).is_some()
+}
+
+fn debug_component<T: Component + Debug>(world: &World, id: EntityId) -> Option<String> {
+    world
+        .get_component::<T>(id)
+        .map(|component| format!("{component:?}"))
+}
+
+fn clone_component<T: Component + Clone>(world: &mut World, from: EntityId, to: EntityId) {
+    if let Some(component) = world.get_component::<T>(from).cloned() {
+        world.add_component(to, component);
+    }
+}
+
+/// `type_name` without module paths, e.g. `Vec<Collide>` rather than
+/// `alloc::vec::Vec<game::Collide>`.
+fn short_type_name(full_name: &str) -> String {
+    let mut name = String::new();
+    let mut segment = String::new();
+    for character in full_name.chars() {
+        if character.is_alphanumeric() || character == '_' || character == ':' {
+            segment.push(character);
+        } else {
+            name.push_str(segment.rsplit("::").next().unwrap_or_default());
+            segment.clear();
+            name.push(character);
+        }
+    }
+    name.push_str(segment.rsplit("::").next().unwrap_or_default());
+    name
+}
+
+impl World {
+    /// Makes `T` known to the world's type registry. Registering the same
+    /// type again has no effect.
+    pub fn register_component<T: Component + Debug>(&mut self) {
+        if self.registry.indices.contains_key(&TypeId::of::<T>()) {
+            return;
+        }
+        self.storage_or_insert::<T>();
+        self.registry
+            .indices
+            .insert(TypeId::of::<T>(), self.registry.infos.len());
+        self.registry.infos.push(ComponentInfo {
+            id: ComponentId(self.registry.infos.len()),
+            type_id: TypeId::of::<T>(),
+            name: short_type_name(type_name::<T>()),
+            key: None,
+            remove: remove_component::<T>,
+            debug: debug_component::<T>,
+            clone: None,
+            snapshot: None,
+        });
+    }
+    /// Registers `T` like `register_component` under a stable name of your
+    /// choosing, which `serialize` then uses as its key instead of the Rust
+    /// type name, so save files survive renaming or moving the type.
+    /// Registering `T` again under the same name has no effect. Panics if
+    /// another type already has the name, or `T` already has another one.
+    pub fn register_component_with_name<T: Component + Debug>(&mut self, name: &str) {
+        if let Some(info) = self.registry.iter().find(|info| info.key() == Some(name)) {
+            if info.type_id != TypeId::of::<T>() {
+                panic!(
+                    "component name `{name}` is already registered for `{}`",
+                    info.name
+                );
+            }
+        }
+        self.register_component::<T>();
+        let index = self.registry.indices[&TypeId::of::<T>()];
+        let info = &mut self.registry.infos[index];
+        match &info.key {
+            Some(key) if key != name => panic!(
+                "component `{}` is already registered under the name `{key}`",
+                info.name
+            ),
+            _ => info.key = Some(name.to_string()),
+        }
+    }
+    /// Registers `T` like `register_component` and lets `clone_entity` copy
+    /// it and `snapshot` save it.
+    pub fn register_cloneable<T: Component + Debug + Clone>(&mut self) {
+        self.register_component::<T>();
+        let index = self.registry.indices[&TypeId::of::<T>()];
+        self.registry.infos[index].clone = Some(clone_component::<T>);
+        self.registry.infos[index].snapshot = Some(ComponentSnapshot::of::<T>);
+    }
+    /// Spawns a copy of `id` carrying a clone of each of its components
+    /// registered with `register_cloneable`; any other component is left
+    /// out. `None` if `id` isn't alive.
+    pub fn clone_entity(&mut self, id: EntityId) -> Option<EntityId> {
+        if !self.is_alive(id) {
+            return None;
+        }
+        let clones: Vec<_> = self.registry.iter().filter_map(|info| info.clone).collect();
+        let copy = self.reserve_entity();
+        for clone in clones {
+            clone(self, id, copy);
+        }
+        Some(copy)
+    }
+    pub fn component_info(&self, type_id: TypeId) -> Option<&ComponentInfo> {
+        self.registry.get(type_id)
+    }
+    /// `None` until `T` is registered.
+    pub fn component_id<T: Component>(&self) -> Option<ComponentId> {
+        self.component_info(TypeId::of::<T>())
+            .map(ComponentInfo::id)
+    }
+    pub fn component_info_by_id(&self, component_id: ComponentId) -> Option<&ComponentInfo> {
+        self.registry.get_by_id(component_id)
+    }
+    /// The entity's component of the registered type `component_id`, to be
+    /// downcast by the caller.
+    pub fn get_by_id(&self, id: EntityId, component_id: ComponentId) -> Option<&dyn Any> {
+        let type_id = self.registry.get_by_id(component_id)?.type_id;
+        self.storage_by_type_id(type_id)?.get_any(id)
+    }
+    /// Calls `f` with the name and value of each registered component the
+    /// entity has, in registration order. Unregistered components are
+    /// skipped, as in `debug_dump`.
+    pub fn for_each_component_of(&self, id: EntityId, mut f: impl FnMut(&str, &dyn Any)) {
+        for info in self.registry.iter() {
+            if let Some(component) = self
+                .storage_by_type_id(info.type_id)
+                .and_then(|storage| storage.get_any(id))
+            {
+                f(&info.name, component);
+            }
+        }
+    }
+    /// One line per live entity followed by an indented line for each of its
+    /// registered components, formatted with `Debug`. Unregistered
+    /// components don't show up.
+    pub fn debug_dump(&self) -> String {
+        let mut dump = String::new();
+        for id in self.iter_entities() {
+            dump.push_str(&format!("Entity {id}\n"));
+            for info in self.registry.iter() {
+                if let Some(component) = info.debug(self, id) {
+                    dump.push_str(&format!("    {}: {component}\n", info.name));
+                }
+            }
+        }
+        dump
+    }
+    /// Registered component types, in registration order.
+    pub fn registered_components(&self) -> impl Iterator<Item = &ComponentInfo> + '_ {
+        self.registry.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Component, World};
+
+    #[derive(Debug)]
+    struct Health;
+    impl Component for Health {}
+
+    #[derive(Debug)]
+    struct Mana;
+    impl Component for Mana {}
+
+    #[test]
+    fn registering_the_same_name_twice_is_fine() {
+        let mut world = World::new();
+        world.register_component_with_name::<Health>("health");
+        world.register_component_with_name::<Health>("health");
+        let keys: Vec<_> = world
+            .registered_components()
+            .map(|info| info.key())
+            .collect();
+        assert_eq!(keys, vec![Some("health")]);
+    }
+
+    #[test]
+    #[should_panic(expected = "component name `health` is already registered")]
+    fn a_name_taken_by_another_type_panics() {
+        let mut world = World::new();
+        world.register_component_with_name::<Health>("health");
+        world.register_component_with_name::<Mana>("health");
+    }
+
+    #[test]
+    #[should_panic(expected = "is already registered under the name `health`")]
+    fn renaming_a_registered_type_panics() {
+        let mut world = World::new();
+        world.register_component_with_name::<Health>("health");
+        world.register_component_with_name::<Health>("hp");
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Position(i32);
+    impl Component for Position {}
+
+    #[test]
+    fn clone_entity_copies_cloneable_components_to_a_fresh_id() {
+        let mut world = World::new();
+        world.register_cloneable::<Position>();
+        let original = world.spawn().insert(Position(3)).insert(Mana).id();
+        let copy = world.clone_entity(original).unwrap();
+        assert_ne!(copy, original);
+        assert_eq!(world.get_component::<Position>(copy), Some(&Position(3)));
+        assert!(!world.has_component::<Mana>(copy));
+        world.get_component_mut::<Position>(copy).unwrap().0 = 4;
+        assert_eq!(
+            world.get_component::<Position>(original),
+            Some(&Position(3))
+        );
+        world.despawn(original);
+        assert_eq!(world.clone_entity(original), None);
+    }
+}
@@ -0,0 +1,67 @@
+use std::cell::{Ref, RefMut};
+use std::ops::{Deref, DerefMut};
+
+pub struct Res<'w, R: 'static> {
+    value: Ref<'w, R>,
+}
+
+impl<'w, R: 'static> Res<'w, R> {
+    pub(crate) fn new(value: Ref<'w, R>) -> Self {
+        Self { value }
+    }
+}
+
+impl<R: 'static> Deref for Res<'_, R> {
+    type Target = R;
+    fn deref(&self) -> &R {
+        &self.value
+    }
+}
+
+pub struct ResMut<'w, R: 'static> {
+    value: RefMut<'w, R>,
+}
+
+impl<'w, R: 'static> ResMut<'w, R> {
+    pub(crate) fn new(value: RefMut<'w, R>) -> Self {
+        Self { value }
+    }
+}
+
+impl<R: 'static> Deref for ResMut<'_, R> {
+    type Target = R;
+    fn deref(&self) -> &R {
+        &self.value
+    }
+}
+
+impl<R: 'static> DerefMut for ResMut<'_, R> {
+    fn deref_mut(&mut self) -> &mut R {
+        &mut self.value
+    }
+}
+
+/// System parameter holding state private to one system, kept between runs
+/// and starting out as `T::default()`.
+pub struct Local<'s, T: 'static> {
+    value: RefMut<'s, T>,
+}
+
+impl<'s, T: 'static> Local<'s, T> {
+    pub(crate) fn new(value: RefMut<'s, T>) -> Self {
+        Self { value }
+    }
+}
+
+impl<T: 'static> Deref for Local<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: 'static> DerefMut for Local<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
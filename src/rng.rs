@@ -0,0 +1,78 @@
+/// Deterministic SplitMix64 generator. Insert one per world with
+/// `world.insert_resource(Rng::new(seed))` and `fork` a substream for each
+/// system that needs its own.
+pub struct Rng {
+    seed: u64,
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: seed }
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// Derives a generator from this one's seed and `label`, independent of
+    /// how many numbers have already been drawn from `self`.
+    pub fn fork(&self, label: &str) -> Rng {
+        let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+        for byte in label.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        Rng::new(mix(self.seed ^ hash))
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.state)
+    }
+    /// Uniform in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+    /// Uniform in `[low, high)`. Panics if the range is empty.
+    pub fn range(&mut self, low: u64, high: u64) -> u64 {
+        assert!(low < high, "empty range {low}..{high}");
+        low + self.next_u64() % (high - low)
+    }
+}
+
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Rng, World};
+
+    fn draw(seed: u64) -> (Vec<u64>, Vec<u64>) {
+        let mut world = World::new();
+        world.insert_resource(Rng::new(seed));
+        let rng = world.resource::<Rng>();
+        let mut physics = rng.fork("physics");
+        let mut ai = rng.fork("ai");
+        (
+            (0..8).map(|_| physics.next_u64()).collect(),
+            (0..8).map(|_| ai.next_u64()).collect(),
+        )
+    }
+
+    #[test]
+    fn forks_are_reproducible_per_seed() {
+        let (physics, ai) = draw(42);
+        assert_eq!(draw(42), (physics.clone(), ai.clone()));
+        assert_ne!(physics, ai);
+        assert_ne!(draw(43).0, physics);
+    }
+
+    #[test]
+    fn fork_ignores_draws_from_the_parent() {
+        let mut rng = Rng::new(7);
+        let before = rng.fork("physics").next_u64();
+        rng.next_u64();
+        assert_eq!(rng.fork("physics").next_u64(), before);
+    }
+}
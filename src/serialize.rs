@@ -0,0 +1,195 @@
+use crate::component::Component;
+use crate::world::{EntityId, World};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::any::{type_name, TypeId};
+
+pub(crate) struct ComponentSerializer {
+    type_id: TypeId,
+    name: &'static str,
+    serialize: fn(&World) -> Result<Value, serde_json::Error>,
+    deserialize: fn(&mut World, EntityId, Value) -> Result<(), serde_json::Error>,
+}
+
+/// Written as its `Display` form, e.g. `"3v0"`, so components holding ids
+/// round-trip too.
+impl Serialize for EntityId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for EntityId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+fn serialize_storage<T: Component + Serialize>(world: &World) -> Result<Value, serde_json::Error> {
+    let mut components = Map::new();
+    if let Some(storage) = world.storage::<T>() {
+        for (id, component) in storage.iter() {
+            components.insert(id.to_string(), serde_json::to_value(component)?);
+        }
+    }
+    Ok(Value::Object(components))
+}
+
+fn deserialize_component<T: Component + DeserializeOwned>(
+    world: &mut World,
+    id: EntityId,
+    value: Value,
+) -> Result<(), serde_json::Error> {
+    world.add_component(id, serde_json::from_value::<T>(value)?);
+    Ok(())
+}
+
+impl World {
+    /// Includes `T` in `serialize`/`deserialize`, keyed by its type name or
+    /// by the name given to `register_component_with_name`. Registering the
+    /// same type twice has no effect.
+    pub fn register_serializable<T: Component + Serialize + DeserializeOwned>(&mut self) {
+        if self
+            .serializers
+            .iter()
+            .any(|serializer| serializer.type_id == TypeId::of::<T>())
+        {
+            return;
+        }
+        self.serializers.push(ComponentSerializer {
+            type_id: TypeId::of::<T>(),
+            name: type_name::<T>(),
+            serialize: serialize_storage::<T>,
+            deserialize: deserialize_component::<T>,
+        });
+    }
+    /// Writes every live entity id plus, per registered type, a map from
+    /// entity id to component value. Unregistered components are left out.
+    pub fn serialize(&self) -> Result<String, serde_json::Error> {
+        let entities: Vec<EntityId> = self.iter_entities().collect();
+        let mut components = Map::new();
+        for serializer in &self.serializers {
+            components.insert(
+                self.serialization_key(serializer).to_string(),
+                (serializer.serialize)(self)?,
+            );
+        }
+        let mut document = Map::new();
+        document.insert("entities".to_string(), serde_json::to_value(entities)?);
+        document.insert("components".to_string(), Value::Object(components));
+        serde_json::to_string(&Value::Object(document))
+    }
+    /// Replaces every entity with the ones in `json`, keeping their ids.
+    /// Registered types missing from the document are simply absent, and
+    /// types in the document that aren't registered are ignored.
+    pub fn deserialize(&mut self, json: &str) -> Result<(), serde_json::Error> {
+        let mut document: Map<String, Value> = serde_json::from_str(json)?;
+        let entities: Vec<EntityId> = serde_json::from_value(
+            document
+                .remove("entities")
+                .unwrap_or(Value::Array(Vec::new())),
+        )?;
+        let mut components: Map<String, Value> = serde_json::from_value(
+            document
+                .remove("components")
+                .unwrap_or(Value::Object(Map::new())),
+        )?;
+
+        self.clear();
+        for id in entities {
+            self.register_entity(id);
+        }
+        for index in 0..self.serializers.len() {
+            let (key, deserialize) = {
+                let serializer = &self.serializers[index];
+                (
+                    self.serialization_key(serializer).to_string(),
+                    serializer.deserialize,
+                )
+            };
+            let Some(Value::Object(values)) = components.remove(&key) else {
+                continue;
+            };
+            for (id, value) in values {
+                let id = id.parse().map_err(serde::de::Error::custom)?;
+                deserialize(self, id, value)?;
+            }
+        }
+        Ok(())
+    }
+    fn serialization_key(&self, serializer: &ComponentSerializer) -> &str {
+        self.component_info(serializer.type_id)
+            .and_then(|info| info.key())
+            .unwrap_or(serializer.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Component, EntityId, World};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+    impl Component for Health {}
+
+    impl Serialize for Health {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_u32(self.0)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Health {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            u32::deserialize(deserializer).map(Health)
+        }
+    }
+
+    struct Marker;
+    impl Component for Marker {}
+
+    fn registered_world() -> World {
+        let mut world = World::new();
+        world.register_component_with_name::<Health>("health");
+        world.register_serializable::<Health>();
+        world
+    }
+
+    #[test]
+    fn round_trip_keeps_ids_and_registered_components() {
+        let mut world = registered_world();
+        let first = world.spawn().insert(Health(10)).insert(Marker).id();
+        let gone = world.spawn().id();
+        world.despawn(gone);
+        let reused = world.spawn().insert(Health(20)).id();
+        assert_eq!(reused, EntityId::new(gone.slot(), 1));
+
+        let json = world.serialize().unwrap();
+        assert!(json.contains("\"health\""));
+        assert!(!json.contains("Marker"));
+
+        let mut loaded = registered_world();
+        loaded.deserialize(&json).unwrap();
+        assert_eq!(
+            loaded.iter_entities().collect::<Vec<_>>(),
+            vec![first, reused]
+        );
+        assert!(!loaded.is_alive(gone));
+        assert_eq!(loaded.get_component::<Health>(first), Some(&Health(10)));
+        assert_eq!(loaded.get_component::<Health>(reused), Some(&Health(20)));
+        assert!(!loaded.has_any::<Marker>());
+    }
+
+    #[test]
+    fn malformed_documents_are_errors() {
+        let mut world = registered_world();
+        assert!(world.deserialize("{not json").is_err());
+        assert!(world.deserialize(r#"{"entities": ["nope"]}"#).is_err());
+        assert!(world
+            .deserialize(r#"{"entities": ["0v0"], "components": {"health": {"0v0": "ten"}}}"#)
+            .is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::component::Component;
+use crate::hierarchy::{Children, Parent};
+use crate::world::{EntityId, EntityMeta, World};
+use std::any::{Any, TypeId};
+use std::collections::HashSet;
+
+/// A saved copy of a world's entities and their cloneable components, taken
+/// with `World::snapshot`, e.g. to roll back after a network misprediction.
+pub struct WorldSnapshot {
+    ids: HashSet<EntityId>,
+    entities: Vec<EntityMeta>,
+    next_index: usize,
+    free_slots: Vec<usize>,
+    components: Vec<ComponentSnapshot>,
+}
+
+/// Every value of one component type, with how to put them back.
+pub(crate) struct ComponentSnapshot {
+    values: Box<dyn Any>,
+    restore: fn(&mut World, &dyn Any),
+}
+
+impl ComponentSnapshot {
+    pub(crate) fn of<T: Component + Clone>(world: &World) -> Self {
+        let values: Vec<(EntityId, T)> = world
+            .query_including_disabled::<&T>()
+            .with_entities()
+            .map(|(id, component)| (id, component.clone()))
+            .collect();
+        Self {
+            values: Box::new(values),
+            restore: restore_component::<T>,
+        }
+    }
+}
+
+fn restore_component<T: Component + Clone>(world: &mut World, values: &dyn Any) {
+    world.remove_all::<T>();
+    let values = values.downcast_ref::<Vec<(EntityId, T)>>().unwrap();
+    for (id, component) in values {
+        world.add_component(*id, component.clone());
+    }
+}
+
+impl World {
+    /// Saves every entity, id and generation included, along with the
+    /// hierarchy (`Parent` and `Children`) and its components of types
+    /// registered with `register_cloneable`. Other components aren't saved.
+    pub fn snapshot(&self) -> WorldSnapshot {
+        let hierarchy = [TypeId::of::<Parent>(), TypeId::of::<Children>()];
+        let mut components: Vec<ComponentSnapshot> = self
+            .registry
+            .iter()
+            .filter(|&info| !hierarchy.contains(&info.type_id()))
+            .filter_map(|info| info.snapshot)
+            .map(|snapshot| snapshot(self))
+            .collect();
+        components.push(ComponentSnapshot::of::<Parent>(self));
+        components.push(ComponentSnapshot::of::<Children>(self));
+        WorldSnapshot {
+            ids: self.iter_entities().collect(),
+            entities: self.entities.clone(),
+            next_index: self.next_index.get(),
+            free_slots: self.free_slots.borrow().clone(),
+            components,
+        }
+    }
+    /// Puts the world back the way it was when `snapshot` was taken: the
+    /// same entities under the same ids, with the saved components. Entities
+    /// the snapshot doesn't have are despawned; the others keep components
+    /// of types it doesn't cover. Restored components count as changed, and
+    /// observers see them inserted.
+    pub fn restore(&mut self, snapshot: &WorldSnapshot) {
+        let gone: Vec<EntityId> = self
+            .iter_entities()
+            .filter(|id| !snapshot.ids.contains(id))
+            .collect();
+        for id in gone {
+            self.despawn(id);
+        }
+        self.entities = snapshot.entities.clone();
+        self.next_index.set(snapshot.next_index);
+        *self.free_slots.get_mut() = snapshot.free_slots.clone();
+        self.structure_version += 1;
+        for component in &snapshot.components {
+            (component.restore)(self, component.values.as_ref());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Children, Component, Parent, World};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Health(u32);
+    impl Component for Health {}
+
+    #[test]
+    fn restore_rolls_back_components_and_ids() {
+        let mut world = World::new();
+        world.register_cloneable::<Health>();
+        let kept = world.spawn().insert(Health(10)).id();
+        let removed = world.spawn().insert(Health(20)).id();
+        let snapshot = world.snapshot();
+
+        world.get_component_mut::<Health>(kept).unwrap().0 = 1;
+        world.despawn(removed);
+        let added = world.spawn().insert(Health(30)).id();
+        world.restore(&snapshot);
+
+        assert_eq!(world.get_component::<Health>(kept), Some(&Health(10)));
+        assert_eq!(world.get_component::<Health>(removed), Some(&Health(20)));
+        assert!(!world.is_alive(added));
+        assert_eq!(world.validate(), vec![]);
+    }
+
+    #[test]
+    fn restore_rolls_back_the_hierarchy() {
+        let mut world = World::new();
+        let root = world.spawn().id();
+        let child = world.spawn().id();
+        world.set_parent(child, root);
+        let snapshot = world.snapshot();
+
+        let late = world.spawn().id();
+        world.set_parent(late, root);
+        world.despawn(child);
+        world.restore(&snapshot);
+
+        assert_eq!(world.get_component::<Parent>(child), Some(&Parent(root)));
+        assert_eq!(
+            world.get_component::<Children>(root),
+            Some(&Children(vec![child]))
+        );
+        assert!(!world.is_alive(late));
+        assert_eq!(world.validate(), vec![]);
+    }
+}
@@ -0,0 +1,610 @@
+use crate::component::Component;
+use crate::world::{EntityId, World};
+use std::any::{type_name, Any};
+use std::cell::{Cell, RefCell, UnsafeCell};
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::NonNull;
+
+/// How a component type is stored, picked per type with
+/// `World::set_storage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageKind {
+    /// Components packed in an array, with a per-entity index into it. Fast
+    /// to iterate, insert and remove; the default.
+    SparseSet,
+    /// Components in a hash map keyed by entity. Memory grows only with the
+    /// components actually stored, which suits large components few
+    /// entities have, at the cost of slower iteration. Its entries have no
+    /// order, so `Changed` queries on it sort the matches before yielding
+    /// them.
+    Map,
+}
+
+/// Per-type component storage. Zero-sized components (tags like `Player`)
+/// only need a presence bit per entity, so unless another kind is picked
+/// they get a bitset instead of the sparse set.
+pub(crate) struct ComponentStorage<T> {
+    backend: Backend<T>,
+    /// Every change with its tick, oldest first, so `Changed` queries can
+    /// look back over several ticks (one per system run in an `App`)
+    /// without scanning the storage. Entries may be stale or repeated; the
+    /// per-component ticks have the final say.
+    changes: RefCell<VecDeque<(u64, EntityId)>>,
+    /// The oldest tick whose changes are all still in `changes`.
+    changes_from: Cell<u64>,
+}
+
+enum Backend<T> {
+    Dense(DenseStorage<T>),
+    Tag(TagStorage<T>),
+    Map(MapStorage<T>),
+}
+
+impl<T: Component> ComponentStorage<T> {
+    pub(crate) fn new() -> Self {
+        let backend = if mem::size_of::<T>() == 0 {
+            Backend::Tag(TagStorage::new())
+        } else {
+            Backend::Dense(DenseStorage::new())
+        };
+        Self::with_backend(backend)
+    }
+    pub(crate) fn with_kind(kind: StorageKind) -> Self {
+        Self::with_backend(match kind {
+            StorageKind::SparseSet => Backend::Dense(DenseStorage::new()),
+            StorageKind::Map => Backend::Map(MapStorage::new()),
+        })
+    }
+    fn with_backend(backend: Backend<T>) -> Self {
+        Self {
+            backend,
+            changes: RefCell::new(VecDeque::new()),
+            changes_from: Cell::new(0),
+        }
+    }
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        match &mut self.backend {
+            Backend::Dense(dense) => dense.reserve(additional),
+            Backend::Tag(tags) => tags.reserve(additional),
+            Backend::Map(map) => map.components.reserve(additional),
+        }
+    }
+    pub(crate) fn insert(&mut self, id: EntityId, component: T, tick: u64) -> Option<T> {
+        let previous = match &mut self.backend {
+            Backend::Dense(dense) => dense.insert(id, component),
+            Backend::Tag(tags) => tags.insert(id, component),
+            Backend::Map(map) => map.insert(id, component),
+        };
+        self.mark_changed(id, tick);
+        previous
+    }
+    pub(crate) fn remove(&mut self, id: EntityId) -> Option<T> {
+        let component = match &mut self.backend {
+            Backend::Dense(dense) => dense.remove(id),
+            Backend::Tag(tags) => tags.remove(id),
+            Backend::Map(map) => map.remove(id),
+        }?;
+        Some(component)
+    }
+    pub(crate) fn remove_all(&mut self) -> Vec<(EntityId, T)> {
+        self.changes.get_mut().clear();
+        match &mut self.backend {
+            Backend::Dense(dense) => dense.remove_all(),
+            Backend::Tag(tags) => tags.remove_all(),
+            Backend::Map(map) => map.remove_all(),
+        }
+    }
+    /// Every entity with a component here, in no particular order.
+    pub(crate) fn ids(&self) -> Vec<EntityId> {
+        match &self.backend {
+            Backend::Dense(dense) => dense.ids.clone(),
+            Backend::Tag(tags) => tags.ids().collect(),
+            Backend::Map(map) => map.components.keys().copied().collect(),
+        }
+    }
+    pub(crate) fn len(&self) -> usize {
+        match &self.backend {
+            Backend::Dense(dense) => dense.ids.len(),
+            Backend::Tag(tags) => tags
+                .bits
+                .iter()
+                .map(|word| word.count_ones() as usize)
+                .sum(),
+            Backend::Map(map) => map.components.len(),
+        }
+    }
+    pub(crate) fn is_empty(&self) -> bool {
+        match &self.backend {
+            Backend::Dense(dense) => dense.ids.is_empty(),
+            Backend::Tag(tags) => tags.bits.iter().all(|&word| word == 0),
+            Backend::Map(map) => map.components.is_empty(),
+        }
+    }
+    /// Bytes allocated for the components and their bookkeeping, counting
+    /// spare capacity.
+    pub(crate) fn memory_usage(&self) -> usize {
+        let backend = match &self.backend {
+            Backend::Dense(dense) => dense.memory_usage(),
+            Backend::Tag(tags) => tags.memory_usage(),
+            Backend::Map(map) => map.memory_usage(),
+        };
+        backend + self.changes.borrow().capacity() * mem::size_of::<(u64, EntityId)>()
+    }
+    pub(crate) fn contains(&self, id: EntityId) -> bool {
+        match &self.backend {
+            Backend::Dense(dense) => dense.index(id).is_some(),
+            Backend::Tag(tags) => tags.contains(id),
+            Backend::Map(map) => map.components.contains_key(&id),
+        }
+    }
+    #[cfg(feature = "serde")]
+    pub(crate) fn iter(&self) -> Box<dyn Iterator<Item = (EntityId, &T)> + '_> {
+        match &self.backend {
+            Backend::Dense(dense) => Box::new(dense.iter()),
+            Backend::Tag(tags) => Box::new(tags.iter()),
+            Backend::Map(map) => Box::new(map.iter()),
+        }
+    }
+    pub(crate) fn get(&self, id: EntityId) -> Option<&T> {
+        // SAFETY: mutable access is only handed out through
+        // `get_unchecked_mut`, whose callers guarantee it never overlaps
+        // another reference to the same component.
+        unsafe { self.get_unchecked(id) }
+    }
+    pub(crate) fn get_mut(&mut self, id: EntityId) -> Option<&mut T> {
+        // SAFETY: `&mut self` rules out any other reference into the storage.
+        unsafe { self.get_unchecked_mut(id) }
+    }
+    /// # Safety
+    /// The caller must make sure no mutable reference to this entity's
+    /// component is alive while the returned one is used.
+    unsafe fn get_unchecked(&self, id: EntityId) -> Option<&T> {
+        match &self.backend {
+            Backend::Dense(dense) => {
+                let index = dense.index(id)?;
+                Some(&*dense.dense[index].get())
+            }
+            Backend::Tag(tags) => tags.contains(id).then(|| tags.get()),
+            Backend::Map(map) => map
+                .components
+                .get(&id)
+                .map(|(component, _)| &*component.get()),
+        }
+    }
+    /// # Safety
+    /// The caller must make sure no other reference to this entity's
+    /// component is alive while the returned one is used.
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn get_unchecked_mut(&self, id: EntityId) -> Option<&mut T> {
+        match &self.backend {
+            Backend::Dense(dense) => {
+                let index = dense.index(id)?;
+                Some(&mut *dense.dense[index].get())
+            }
+            Backend::Tag(tags) => tags.contains(id).then(|| tags.get_mut()),
+            Backend::Map(map) => map
+                .components
+                .get(&id)
+                .map(|(component, _)| &mut *component.get()),
+        }
+    }
+    pub(crate) fn mark_changed(&self, id: EntityId, tick: u64) {
+        if !self.contains(id) {
+            return;
+        }
+        let previous = match &self.backend {
+            Backend::Dense(dense) => dense.ticks[dense.index(id).unwrap()].replace(tick),
+            Backend::Tag(tags) => tags.set_tick(id, tick),
+            Backend::Map(map) => map.components[&id].1.replace(tick),
+        };
+        if previous != tick {
+            self.log_change(tick, id);
+        }
+    }
+    /// Once the log outgrows the storage, scanning is as cheap as reading
+    /// it, so the older half is dropped and lookups that far back scan.
+    fn log_change(&self, tick: u64, id: EntityId) {
+        let mut changes = self.changes.borrow_mut();
+        changes.push_back((tick, id));
+        let limit = self.len().max(64);
+        if changes.len() <= limit {
+            return;
+        }
+        let mut dropped = 0;
+        while changes.len() > limit / 2 {
+            dropped = changes.pop_front().unwrap().0;
+        }
+        while changes.front().is_some_and(|&(tick, _)| tick == dropped) {
+            changes.pop_front();
+        }
+        self.changes_from.set(dropped + 1);
+    }
+    /// Whether the component changed during any tick after `since`.
+    pub(crate) fn is_changed(&self, id: EntityId, since: u64) -> bool {
+        let tick = match &self.backend {
+            Backend::Dense(dense) => dense.index(id).map(|index| dense.ticks[index].get()),
+            Backend::Tag(tags) => tags.tick(id),
+            Backend::Map(map) => map.components.get(&id).map(|(_, tick)| tick.get()),
+        };
+        tick.is_some_and(|tick| tick > since)
+    }
+    /// Entities whose component changed after `since`, read from the change
+    /// log while it reaches back that far, otherwise by scanning the whole
+    /// storage. Sorted by id, so queries driven by this list visit entities
+    /// in the same order as a full scan.
+    pub(crate) fn changed_entities(&self, since: u64) -> Vec<EntityId> {
+        let mut ids: Vec<EntityId> = if since >= self.changes_from.get() {
+            let changes = self.changes.borrow();
+            let start = changes.partition_point(|&(tick, _)| tick <= since);
+            changes
+                .range(start..)
+                .map(|&(_, id)| id)
+                .filter(|&id| self.is_changed(id, since))
+                .collect()
+        } else {
+            self.ids()
+                .into_iter()
+                .filter(|&id| self.is_changed(id, since))
+                .collect()
+        };
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+struct DenseStorage<T> {
+    sparse: Vec<Option<usize>>,
+    dense: Vec<UnsafeCell<T>>,
+    ids: Vec<EntityId>,
+    ticks: Vec<Cell<u64>>,
+}
+
+impl<T> DenseStorage<T> {
+    fn new() -> Self {
+        Self {
+            sparse: Vec::new(),
+            dense: Vec::new(),
+            ids: Vec::new(),
+            ticks: Vec::new(),
+        }
+    }
+    fn reserve(&mut self, additional: usize) {
+        self.sparse.reserve(additional);
+        self.dense.reserve(additional);
+        self.ids.reserve(additional);
+        self.ticks.reserve(additional);
+    }
+    fn insert(&mut self, id: EntityId, component: T) -> Option<T> {
+        if let Some(index) = self.index(id) {
+            return Some(mem::replace(self.dense[index].get_mut(), component));
+        }
+        if self.sparse.len() <= id.slot() {
+            self.sparse.resize(id.slot() + 1, None);
+        }
+        self.sparse[id.slot()] = Some(self.dense.len());
+        self.dense.push(UnsafeCell::new(component));
+        self.ids.push(id);
+        self.ticks.push(Cell::new(0));
+        None
+    }
+    fn remove(&mut self, id: EntityId) -> Option<T> {
+        let index = self.index(id)?;
+        self.sparse[id.slot()] = None;
+        self.ids.swap_remove(index);
+        self.ticks.swap_remove(index);
+        let component = self.dense.swap_remove(index).into_inner();
+        if let Some(&moved) = self.ids.get(index) {
+            self.sparse[moved.slot()] = Some(index);
+        }
+        Some(component)
+    }
+    fn remove_all(&mut self) -> Vec<(EntityId, T)> {
+        self.sparse.clear();
+        self.ticks.clear();
+        self.ids
+            .drain(..)
+            .zip(self.dense.drain(..).map(UnsafeCell::into_inner))
+            .collect()
+    }
+    #[cfg(feature = "serde")]
+    fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> + '_ {
+        // SAFETY: shared, as in `ComponentStorage::get_unchecked`; see `get`.
+        self.ids
+            .iter()
+            .zip(&self.dense)
+            .map(|(&id, component)| (id, unsafe { &*component.get() }))
+    }
+    /// Where the entity's component sits in `dense`; `None` for stale ids
+    /// whose slot has been reused.
+    fn index(&self, id: EntityId) -> Option<usize> {
+        let index = self.sparse.get(id.slot()).copied().flatten()?;
+        (self.ids[index] == id).then_some(index)
+    }
+    fn memory_usage(&self) -> usize {
+        self.sparse.capacity() * mem::size_of::<Option<usize>>()
+            + self.dense.capacity() * mem::size_of::<T>()
+            + self.ids.capacity() * mem::size_of::<EntityId>()
+            + self.ticks.capacity() * mem::size_of::<u64>()
+    }
+}
+
+/// Presence bits (and change ticks) for a zero-sized component. Inserted values are forgotten
+/// and conjured back on removal, which is sound because a value of a
+/// zero-sized type carries no data and one was moved in for every set bit.
+struct TagStorage<T> {
+    bits: Vec<u64>,
+    generations: Vec<u32>,
+    ticks: RefCell<Vec<u64>>,
+    marker: PhantomData<T>,
+}
+
+impl<T> TagStorage<T> {
+    fn new() -> Self {
+        Self {
+            bits: Vec::new(),
+            generations: Vec::new(),
+            ticks: RefCell::new(Vec::new()),
+            marker: PhantomData,
+        }
+    }
+    fn reserve(&mut self, additional: usize) {
+        self.bits.reserve(additional.div_ceil(64));
+    }
+    fn insert(&mut self, id: EntityId, component: T) -> Option<T> {
+        mem::forget(component);
+        let slot = id.slot();
+        if self.bits.len() <= slot / 64 {
+            self.bits.resize(slot / 64 + 1, 0);
+        }
+        if self.generations.len() <= slot {
+            self.generations.resize(slot + 1, 0);
+        }
+        let previous = self.contains(id).then(Self::conjure);
+        self.bits[slot / 64] |= 1 << (slot % 64);
+        self.generations[slot] = id.generation();
+        previous
+    }
+    fn remove(&mut self, id: EntityId) -> Option<T> {
+        if !self.contains(id) {
+            return None;
+        }
+        self.bits[id.slot() / 64] &= !(1 << (id.slot() % 64));
+        if let Some(tick) = self.ticks.get_mut().get_mut(id.slot()) {
+            *tick = 0;
+        }
+        Some(Self::conjure())
+    }
+    fn remove_all(&mut self) -> Vec<(EntityId, T)> {
+        let ids: Vec<EntityId> = self.ids().collect();
+        self.bits.clear();
+        self.generations.clear();
+        self.ticks.get_mut().clear();
+        ids.into_iter().map(|id| (id, Self::conjure())).collect()
+    }
+    fn contains(&self, id: EntityId) -> bool {
+        self.contains_slot(id.slot()) && self.generations[id.slot()] == id.generation()
+    }
+    fn contains_slot(&self, slot: usize) -> bool {
+        self.bits
+            .get(slot / 64)
+            .is_some_and(|word| word & (1 << (slot % 64)) != 0)
+    }
+    /// Returns the tick the entity last changed at before this.
+    fn set_tick(&self, id: EntityId, tick: u64) -> u64 {
+        let mut ticks = self.ticks.borrow_mut();
+        if ticks.len() <= id.slot() {
+            ticks.resize(id.slot() + 1, 0);
+        }
+        mem::replace(&mut ticks[id.slot()], tick)
+    }
+    fn tick(&self, id: EntityId) -> Option<u64> {
+        self.contains(id)
+            .then(|| self.ticks.borrow().get(id.slot()).copied().unwrap_or(0))
+    }
+    fn memory_usage(&self) -> usize {
+        self.bits.capacity() * mem::size_of::<u64>()
+            + self.generations.capacity() * mem::size_of::<u32>()
+            + self.ticks.borrow().capacity() * mem::size_of::<u64>()
+    }
+    fn ids(&self) -> impl Iterator<Item = EntityId> + '_ {
+        (0..self.bits.len() * 64)
+            .filter(|&slot| self.contains_slot(slot))
+            .map(|slot| EntityId::new(slot, self.generations[slot]))
+    }
+    #[cfg(feature = "serde")]
+    fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> + '_ {
+        // SAFETY: see `get`.
+        self.ids().map(|id| (id, unsafe { self.get() }))
+    }
+    /// # Safety
+    /// Only valid while some bit is set, i.e. while a value is "stored".
+    unsafe fn get(&self) -> &T {
+        &*NonNull::<T>::dangling().as_ptr()
+    }
+    /// # Safety
+    /// Only valid while some bit is set, i.e. while a value is "stored".
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn get_mut(&self) -> &mut T {
+        NonNull::<T>::dangling().as_mut()
+    }
+    fn conjure() -> T {
+        // SAFETY: `T` is zero-sized, so reading it touches no memory.
+        unsafe { NonNull::<T>::dangling().as_ptr().read() }
+    }
+}
+
+impl<T> Drop for TagStorage<T> {
+    fn drop(&mut self) {
+        if mem::needs_drop::<T>() {
+            drop(self.remove_all());
+        }
+    }
+}
+
+struct MapStorage<T> {
+    components: HashMap<EntityId, (UnsafeCell<T>, Cell<u64>)>,
+}
+
+impl<T> MapStorage<T> {
+    fn new() -> Self {
+        Self {
+            components: HashMap::new(),
+        }
+    }
+    fn insert(&mut self, id: EntityId, component: T) -> Option<T> {
+        if let Some((previous, _)) = self.components.get_mut(&id) {
+            return Some(mem::replace(previous.get_mut(), component));
+        }
+        self.components
+            .insert(id, (UnsafeCell::new(component), Cell::new(0)));
+        None
+    }
+    fn remove(&mut self, id: EntityId) -> Option<T> {
+        self.components
+            .remove(&id)
+            .map(|(component, _)| component.into_inner())
+    }
+    fn remove_all(&mut self) -> Vec<(EntityId, T)> {
+        self.components
+            .drain()
+            .map(|(id, (component, _))| (id, component.into_inner()))
+            .collect()
+    }
+    #[cfg(feature = "serde")]
+    fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> + '_ {
+        // SAFETY: shared, as in `ComponentStorage::get_unchecked`; see `get`.
+        self.components
+            .iter()
+            .map(|(&id, (component, _))| (id, unsafe { &*component.get() }))
+    }
+    fn memory_usage(&self) -> usize {
+        self.components.capacity() * mem::size_of::<(EntityId, (UnsafeCell<T>, Cell<u64>))>()
+    }
+}
+
+pub(crate) trait AnyStorage {
+    fn type_name(&self) -> &'static str;
+    fn contains_entity(&self, id: EntityId) -> bool;
+    #[cfg(any(debug_assertions, test))]
+    fn entity_ids(&self) -> Vec<EntityId>;
+    fn memory_usage(&self) -> usize;
+    fn get_any(&self, id: EntityId) -> Option<&dyn Any>;
+    fn remove_entity(&mut self, id: EntityId);
+    fn clear(&mut self);
+    /// Empties the storage into `world`, moving each component to the id
+    /// `ids` maps its entity to. Components of unmapped entities are
+    /// dropped.
+    fn move_into(&mut self, world: &mut World, ids: &HashMap<EntityId, EntityId>);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Component> AnyStorage for ComponentStorage<T> {
+    fn type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+    fn contains_entity(&self, id: EntityId) -> bool {
+        self.contains(id)
+    }
+    #[cfg(any(debug_assertions, test))]
+    fn entity_ids(&self) -> Vec<EntityId> {
+        self.ids()
+    }
+    fn memory_usage(&self) -> usize {
+        ComponentStorage::memory_usage(self)
+    }
+    fn get_any(&self, id: EntityId) -> Option<&dyn Any> {
+        self.get(id).map(|component| component as &dyn Any)
+    }
+    fn remove_entity(&mut self, id: EntityId) {
+        self.remove(id);
+    }
+    fn clear(&mut self) {
+        self.remove_all();
+    }
+    fn move_into(&mut self, world: &mut World, ids: &HashMap<EntityId, EntityId>) {
+        for (id, component) in self.remove_all() {
+            if let Some(&new_id) = ids.get(&id) {
+                world.add_component(new_id, component);
+            }
+        }
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Position(u32);
+    impl Component for Position {}
+
+    struct Marker;
+    impl Component for Marker {}
+
+    fn storage_with(count: usize) -> ComponentStorage<Position> {
+        let mut storage = ComponentStorage::new();
+        for slot in 0..count {
+            storage.insert(EntityId::new(slot, 0), Position(slot as u32), 1);
+        }
+        storage
+    }
+
+    #[test]
+    fn changes_over_several_ticks_come_from_the_log() {
+        let storage = storage_with(1000);
+        storage.mark_changed(EntityId::new(7, 0), 5);
+        storage.mark_changed(EntityId::new(3, 0), 6);
+        storage.mark_changed(EntityId::new(7, 0), 8);
+        assert!(storage.changes_from.get() <= 4);
+        assert_eq!(
+            storage.changed_entities(4),
+            vec![EntityId::new(3, 0), EntityId::new(7, 0)]
+        );
+        assert_eq!(storage.changed_entities(6), vec![EntityId::new(7, 0)]);
+        assert_eq!(storage.changed_entities(8), vec![]);
+    }
+
+    #[test]
+    fn trimmed_log_falls_back_to_scanning() {
+        let storage = storage_with(10);
+        for tick in 2..200 {
+            storage.mark_changed(EntityId::new(tick as usize % 10, 0), tick);
+        }
+        assert!(storage.changes_from.get() > 2);
+        assert!(storage.changes.borrow().len() <= 64);
+        assert_eq!(storage.changed_entities(1).len(), 10);
+        assert_eq!(storage.changed_entities(198), vec![EntityId::new(9, 0)]);
+    }
+
+    #[test]
+    fn removed_components_drop_out_of_changes() {
+        let mut storage = storage_with(3);
+        storage.mark_changed(EntityId::new(1, 0), 2);
+        let removed = storage.remove(EntityId::new(1, 0));
+        assert_eq!(removed.map(|position| position.0), Some(1));
+        assert_eq!(storage.changed_entities(1), vec![]);
+    }
+
+    #[test]
+    fn tags_take_less_memory_than_a_sparse_set() {
+        let mut tags = ComponentStorage::<Marker>::new();
+        let mut sparse = ComponentStorage::<Marker>::with_kind(StorageKind::SparseSet);
+        for slot in 0..1000 {
+            tags.insert(EntityId::new(slot, 0), Marker, 1);
+            sparse.insert(EntityId::new(slot, 0), Marker, 1);
+        }
+        assert!(matches!(tags.backend, Backend::Tag(_)));
+        assert_eq!(tags.ids(), sparse.ids());
+        assert!(tags.memory_usage() < sparse.memory_usage());
+    }
+}
@@ -0,0 +1,372 @@
+use crate::command::Commands;
+use crate::query::{fetch_components, ComponentCombination, Query, QueryAccess, QueryError};
+use crate::resource::{Local, Res, ResMut};
+use crate::world::World;
+use std::any::{type_name, TypeId};
+use std::cell::{Cell, RefCell};
+
+pub trait SystemParam {
+    type Item<'w>;
+    /// Kept by the system between runs, e.g. the value behind a `Local`.
+    type State: Default + 'static;
+    fn fetch<'w>(state: &'w Self::State, world: &'w World) -> Self::Item<'w>;
+    /// Records what `fetch` reads and writes. Parameters that touch no
+    /// world data record nothing.
+    fn access(_access: &mut SystemAccess) {}
+}
+
+/// The component and resource types a system reads and writes, derived
+/// from its parameter types; see `System::access` and the `#[system]`
+/// attribute.
+///
+/// Within one system the same rule as for a single query applies: a type
+/// that is written may not be accessed in any other way, so deriving the
+/// access of e.g. `fn(Vec<&mut A>, Vec<&A>)` panics.
+#[derive(Debug, Default, Clone)]
+pub struct SystemAccess {
+    components: QueryAccess,
+    resource_reads: Vec<TypeId>,
+    resource_writes: Vec<TypeId>,
+    resource_names: Vec<(TypeId, &'static str)>,
+}
+
+impl SystemAccess {
+    /// The access of `system`, e.g. a function item.
+    pub fn of<Params, S: System<Params>>(_system: &S) -> Self {
+        S::access()
+    }
+    pub fn components(&self) -> &QueryAccess {
+        &self.components
+    }
+    pub fn resource_reads(&self) -> &[TypeId] {
+        &self.resource_reads
+    }
+    pub fn resource_writes(&self) -> &[TypeId] {
+        &self.resource_writes
+    }
+    pub fn add_query<Q: ComponentCombination>(&mut self) {
+        Q::access(&mut self.components);
+    }
+    pub fn add_resource_read<R: 'static>(&mut self) {
+        let type_id = TypeId::of::<R>();
+        if self.resource_writes.contains(&type_id) {
+            Self::resource_conflict::<R>();
+        }
+        if !self.resource_reads.contains(&type_id) {
+            self.resource_reads.push(type_id);
+            self.resource_names.push((type_id, type_name::<R>()));
+        }
+    }
+    pub fn add_resource_write<R: 'static>(&mut self) {
+        let type_id = TypeId::of::<R>();
+        if self.resource_writes.contains(&type_id) || self.resource_reads.contains(&type_id) {
+            Self::resource_conflict::<R>();
+        }
+        self.resource_writes.push(type_id);
+        self.resource_names.push((type_id, type_name::<R>()));
+    }
+    /// Whether one of the two systems writes something the other reads or
+    /// writes, so they couldn't safely run at the same time.
+    pub fn conflicts_with(&self, other: &SystemAccess) -> bool {
+        !self.conflicting_types(other).is_empty()
+    }
+    /// The names of the component and resource types behind
+    /// `conflicts_with`: each one is written by one system and read or
+    /// written by the other.
+    pub fn conflicting_types(&self, other: &SystemAccess) -> Vec<&'static str> {
+        fn overlaps<'a>(
+            writes: &'a [TypeId],
+            reads: &'a [TypeId],
+            other_writes: &'a [TypeId],
+        ) -> impl Iterator<Item = TypeId> + 'a {
+            writes
+                .iter()
+                .filter(|type_id| reads.contains(type_id) || other_writes.contains(type_id))
+                .copied()
+        }
+        let components = overlaps(
+            self.components.writes(),
+            other.components.reads(),
+            other.components.writes(),
+        )
+        .chain(overlaps(
+            other.components.writes(),
+            self.components.reads(),
+            self.components.writes(),
+        ));
+        let resources = overlaps(
+            &self.resource_writes,
+            &other.resource_reads,
+            &other.resource_writes,
+        )
+        .chain(overlaps(
+            &other.resource_writes,
+            &self.resource_reads,
+            &self.resource_writes,
+        ));
+
+        let mut names = Vec::new();
+        for type_id in components {
+            if let Some(name) = self.components.name_of(type_id) {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        for type_id in resources {
+            if let Some(name) = self.resource_name_of(type_id) {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+    fn resource_name_of(&self, type_id: TypeId) -> Option<&'static str> {
+        self.resource_names
+            .iter()
+            .find(|(id, _)| *id == type_id)
+            .map(|&(_, name)| name)
+    }
+    fn resource_conflict<R>() -> ! {
+        panic!(
+            "system accesses resource `{}` mutably while also accessing it elsewhere",
+            type_name::<R>()
+        )
+    }
+}
+
+/// Problems found while fetching `Vec<T>` parameters, e.g. a query naming
+/// a component type the world has never stored. `App` inserts it on its
+/// first frame; remove it to stop collecting. Each system reports a given
+/// problem once.
+#[derive(Debug, Default)]
+pub struct QueryWarnings(pub Vec<QueryError>);
+
+/// A query naming a component type the world has never stored yields
+/// nothing, recording the error in `QueryWarnings` if that resource exists.
+impl<T: ComponentCombination> SystemParam for Vec<T> {
+    type Item<'w> = Vec<T::Item<'w>>;
+    /// Whether the error was recorded already.
+    type State = Cell<bool>;
+    fn fetch<'w>(warned: &'w Cell<bool>, world: &'w World) -> Self::Item<'w> {
+        fetch_components::<T>(world).unwrap_or_else(|error| {
+            if !warned.get() {
+                if let Some(mut warnings) = world.get_resource_mut::<QueryWarnings>() {
+                    warnings.0.push(error);
+                    warned.set(true);
+                }
+            }
+            Vec::new()
+        })
+    }
+    fn access(access: &mut SystemAccess) {
+        access.add_query::<T>();
+    }
+}
+
+/// A lazy alternative to `Vec<T>`: nothing is fetched until the system
+/// iterates it, e.g. with `for collide in &query`.
+impl<Q: ComponentCombination> SystemParam for Query<'_, Q> {
+    type Item<'w> = Query<'w, Q>;
+    type State = ();
+    fn fetch<'w>(_state: &'w (), world: &'w World) -> Self::Item<'w> {
+        Query::new(world, false)
+    }
+    fn access(access: &mut SystemAccess) {
+        access.add_query::<Q>();
+    }
+}
+
+impl<R: 'static> SystemParam for Res<'_, R> {
+    type Item<'w> = Res<'w, R>;
+    type State = ();
+    fn fetch<'w>(_state: &'w (), world: &'w World) -> Self::Item<'w> {
+        world.resource::<R>()
+    }
+    fn access(access: &mut SystemAccess) {
+        access.add_resource_read::<R>();
+    }
+}
+
+impl<R: 'static> SystemParam for ResMut<'_, R> {
+    type Item<'w> = ResMut<'w, R>;
+    type State = ();
+    fn fetch<'w>(_state: &'w (), world: &'w World) -> Self::Item<'w> {
+        world.resource_mut::<R>()
+    }
+    fn access(access: &mut SystemAccess) {
+        access.add_resource_write::<R>();
+    }
+}
+
+impl<T: Default + 'static> SystemParam for Local<'_, T> {
+    type Item<'w> = Local<'w, T>;
+    type State = RefCell<T>;
+    fn fetch<'w>(state: &'w RefCell<T>, _world: &'w World) -> Self::Item<'w> {
+        Local::new(state.borrow_mut())
+    }
+}
+
+impl SystemParam for Commands<'_> {
+    type Item<'w> = Commands<'w>;
+    type State = ();
+    fn fetch<'w>(_state: &'w (), world: &'w World) -> Self::Item<'w> {
+        world.commands()
+    }
+}
+
+/// A function whose parameters are all `SystemParam`s. Each run fetches
+/// them from the `&World` it's given, so `fn(Vec<&MoveTo>)` is written with
+/// elided lifetimes and gets references valid for that one run; only the
+/// function itself must be `'static` to be stored in an `App`.
+pub trait System<Params> {
+    /// Every parameter's `SystemParam::State`, created once per registered
+    /// system.
+    type State: Default + 'static;
+    fn run(&self, state: &Self::State, world: &World);
+    /// Everything the parameters read and write. Panics if two parameters
+    /// conflict, like `Vec<&mut A>` next to `Vec<&A>`.
+    fn access() -> SystemAccess;
+}
+
+/// Something `App::add_startup_system` can run once: a regular system, or a
+/// closure taking `&mut World` for direct setup.
+pub trait StartupSystem<Params> {
+    fn run_startup(self, world: &mut World);
+    /// What the system reads and writes; nothing for a `&mut World`
+    /// closure, which has the world to itself.
+    fn access() -> SystemAccess {
+        SystemAccess::default()
+    }
+}
+
+impl<F: FnOnce(&mut World)> StartupSystem<fn(&mut World)> for F {
+    fn run_startup(self, world: &mut World) {
+        self(world);
+    }
+}
+
+macro_rules! impl_system {
+    ($($param:ident),+) => {
+        impl<F, $($param: SystemParam),+> System<($($param,)+)> for F
+        where
+            F: Fn($($param),+) + for<'w> Fn($($param::Item<'w>),+),
+        {
+            type State = ($($param::State,)+);
+            #[allow(non_snake_case)]
+            fn run(&self, state: &Self::State, world: &World) {
+                fn call<$($param),+>(system: impl Fn($($param),+), $($param: $param),+) {
+                    system($($param),+);
+                }
+                let ($($param,)+) = state;
+                call(self, $($param::fetch($param, world)),+);
+            }
+            fn access() -> SystemAccess {
+                let mut access = SystemAccess::default();
+                $($param::access(&mut access);)+
+                access
+            }
+        }
+
+        impl<F, $($param: SystemParam),+> StartupSystem<($($param,)+)> for F
+        where
+            F: System<($($param,)+)>,
+        {
+            fn run_startup(self, world: &mut World) {
+                self.run(&Default::default(), world);
+            }
+            fn access() -> SystemAccess {
+                <F as System<($($param,)+)>>::access()
+            }
+        }
+    };
+}
+
+impl_system!(A);
+impl_system!(A, B);
+impl_system!(A, B, C);
+impl_system!(A, B, C, D);
+impl_system!(A, B, C, D, E);
+impl_system!(A, B, C, D, E, G);
+
+#[cfg(test)]
+mod tests {
+    use super::QueryWarnings;
+    use crate::{system, App, Component, QueryError, Res, ResMut, SystemAccess, World};
+    use std::any::TypeId;
+
+    struct Position(i32);
+    impl Component for Position {}
+
+    struct Velocity(i32);
+    impl Component for Velocity {}
+
+    struct Gravity(i32);
+
+    #[system]
+    fn apply_velocity(moving: Vec<(&mut Position, &Velocity)>, gravity: Res<Gravity>) {
+        for (position, velocity) in moving {
+            position.0 += velocity.0 + gravity.0;
+        }
+    }
+
+    #[system]
+    fn read_positions(positions: Vec<&Position>) {
+        assert!(positions.iter().all(|position| position.0 >= 0));
+    }
+
+    #[system]
+    fn tune_gravity(mut gravity: ResMut<Gravity>) {
+        gravity.0 -= 1;
+    }
+
+    #[test]
+    fn derived_access_matches_the_parameters() {
+        let access = apply_velocity_access();
+        assert_eq!(access.components().writes(), [TypeId::of::<Position>()]);
+        assert_eq!(access.components().reads(), [TypeId::of::<Velocity>()]);
+        assert_eq!(access.resource_reads(), [TypeId::of::<Gravity>()]);
+        assert!(access.resource_writes().is_empty());
+        assert_eq!(
+            SystemAccess::of(&apply_velocity).components().writes(),
+            access.components().writes()
+        );
+    }
+
+    #[test]
+    fn derived_accesses_conflict_on_shared_types() {
+        let physics = apply_velocity_access();
+        assert!(physics.conflicts_with(&read_positions_access()));
+        assert!(physics.conflicts_with(&tune_gravity_access()));
+        assert!(!read_positions_access().conflicts_with(&tune_gravity_access()));
+    }
+
+    #[test]
+    fn unknown_components_are_recorded_once_per_system() {
+        let mut world = World::new();
+        let mut app = App::new();
+        app.add_system(|positions: Vec<&Position>| assert!(positions.is_empty()));
+        for _ in 0..3 {
+            app.run_once(&mut world);
+        }
+        assert_eq!(
+            world.resource::<QueryWarnings>().0,
+            vec![QueryError::UnknownComponent(
+                std::any::type_name::<Position>()
+            )]
+        );
+    }
+
+    #[test]
+    fn removing_query_warnings_silences_them() {
+        let mut world = World::new();
+        let mut app = App::new();
+        app.add_system(|positions: Vec<&Position>| assert!(positions.is_empty()));
+        app.run_once(&mut world);
+        let warnings = world.remove_resource::<QueryWarnings>().unwrap();
+        assert_eq!(warnings.0.len(), 1);
+        app.run_once(&mut world);
+        assert!(!world.contains_resource::<QueryWarnings>());
+    }
+}
@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+/// Frame timing, advanced by `App::run` before the systems of each frame.
+#[derive(Default)]
+pub struct Time {
+    delta: Duration,
+    elapsed: Duration,
+    alpha: f32,
+}
+
+impl Time {
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+    /// How far real time has progressed into the next fixed step, in
+    /// `[0, 1)`, for interpolating rendering. Always 0 without
+    /// `App::FIXED_UPDATE` systems.
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+    pub(crate) fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+    /// During a fixed step, `delta` is the step while `elapsed` stays at
+    /// the start of the frame.
+    pub(crate) fn set_delta(&mut self, delta: Duration) {
+        self.delta = delta;
+    }
+    pub(crate) fn advance(&mut self, delta: Duration) {
+        self.delta = delta;
+        self.elapsed += delta;
+    }
+}
@@ -0,0 +1,203 @@
+use crate::hierarchy::{Children, Parent};
+use crate::world::{EntityId, World};
+use std::fmt;
+
+/// A broken internal invariant found by `World::validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsistencyError {
+    /// A storage holds a component for an entity that isn't alive.
+    DeadComponentOwner {
+        component: &'static str,
+        id: EntityId,
+    },
+    /// A storage holds a component under an older or newer generation of a
+    /// live entity's slot.
+    GenerationMismatch {
+        component: &'static str,
+        id: EntityId,
+        live: EntityId,
+    },
+    /// The free list would hand out the slot of a live entity.
+    LiveFreeSlot(EntityId),
+    /// `child`'s `Parent` points at an entity that isn't alive.
+    DanglingParent { child: EntityId, parent: EntityId },
+    /// `child`'s `Parent` points at `parent`, whose `Children` don't list it.
+    MissingChild { child: EntityId, parent: EntityId },
+    /// `parent`'s `Children` list an entity that isn't alive.
+    DanglingChild { parent: EntityId, child: EntityId },
+    /// `parent`'s `Children` list `child`, whose `Parent` is something else.
+    WrongParent { parent: EntityId, child: EntityId },
+}
+
+impl fmt::Display for ConsistencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsistencyError::DeadComponentOwner { component, id } => {
+                write!(f, "`{component}` stored for dead entity {id}")
+            }
+            ConsistencyError::GenerationMismatch {
+                component,
+                id,
+                live,
+            } => write!(
+                f,
+                "`{component}` stored for {id}, but the slot holds {live}"
+            ),
+            ConsistencyError::LiveFreeSlot(id) => {
+                write!(f, "slot of live entity {id} is on the free list")
+            }
+            ConsistencyError::DanglingParent { child, parent } => {
+                write!(f, "{child} has dead parent {parent}")
+            }
+            ConsistencyError::MissingChild { child, parent } => {
+                write!(
+                    f,
+                    "{child} has parent {parent}, which doesn't list it as a child"
+                )
+            }
+            ConsistencyError::DanglingChild { parent, child } => {
+                write!(f, "{parent} lists dead child {child}")
+            }
+            ConsistencyError::WrongParent { parent, child } => {
+                write!(
+                    f,
+                    "{parent} lists child {child}, whose parent is another entity"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConsistencyError {}
+
+impl World {
+    /// Checks the world's internal bookkeeping and returns every problem
+    /// found: components stored for dead or stale ids, live slots on the
+    /// free list, and `Parent`/`Children` links that don't resolve or don't
+    /// agree. Empty when all is well. Meant for debugging and tests: it
+    /// walks every storage, so keep it out of the frame loop. Only built in
+    /// debug builds and tests.
+    pub fn validate(&self) -> Vec<ConsistencyError> {
+        let mut errors = Vec::new();
+
+        let mut storages: Vec<_> = self.iter_storages().collect();
+        storages.sort_by_key(|storage| storage.type_name());
+        for storage in storages {
+            let component = storage.type_name();
+            for id in storage.entity_ids() {
+                match self.entity_at(id.slot()) {
+                    Some(live) if live == id => {}
+                    Some(live) => errors.push(ConsistencyError::GenerationMismatch {
+                        component,
+                        id,
+                        live,
+                    }),
+                    None => errors.push(ConsistencyError::DeadComponentOwner { component, id }),
+                }
+            }
+        }
+
+        let mut free_slots = self.free_slots.borrow().clone();
+        free_slots.sort_unstable();
+        for slot in free_slots {
+            if let Some(id) = self.entity_at(slot) {
+                errors.push(ConsistencyError::LiveFreeSlot(id));
+            }
+        }
+
+        for (child, &Parent(parent)) in self.query_including_disabled::<&Parent>().with_entities() {
+            if !self.is_alive(parent) {
+                errors.push(ConsistencyError::DanglingParent { child, parent });
+            } else if !self
+                .get_component::<Children>(parent)
+                .is_some_and(|children| children.0.contains(&child))
+            {
+                errors.push(ConsistencyError::MissingChild { child, parent });
+            }
+        }
+        for (parent, children) in self.query_including_disabled::<&Children>().with_entities() {
+            for &child in &children.0 {
+                if !self.is_alive(child) {
+                    errors.push(ConsistencyError::DanglingChild { parent, child });
+                } else if !matches!(self.get_component::<Parent>(child), Some(&Parent(p)) if p == parent)
+                {
+                    errors.push(ConsistencyError::WrongParent { parent, child });
+                }
+            }
+        }
+
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConsistencyError;
+    use crate::{Children, Component, EntityId, Parent, World};
+
+    struct Health;
+    impl Component for Health {}
+
+    struct Marker;
+    impl Component for Marker {}
+
+    #[test]
+    fn churn_keeps_the_world_consistent() {
+        let mut world = World::new();
+        let mut ids: Vec<EntityId> = (0..50).map(|_| world.spawn().insert(Health).id()).collect();
+        for round in 0..20 {
+            let parent = ids[round];
+            for &child in &ids[round + 1..round + 4] {
+                world.set_parent(child, parent);
+            }
+            world.add_component(ids[round + 5], Marker);
+            world.remove_component::<Health>(ids[round + 6]);
+            if round % 3 == 0 {
+                world.despawn_recursive(ids[round + 1]);
+            } else {
+                world.despawn(ids[round + 2]);
+            }
+            ids.retain(|&id| world.is_alive(id));
+            ids.push(world.spawn().insert(Health).id());
+            assert_eq!(world.validate(), vec![]);
+        }
+        let snapshot = world.snapshot();
+        for &id in &ids[..10] {
+            world.despawn(id);
+        }
+        world.restore(&snapshot);
+        assert_eq!(world.validate(), vec![]);
+        world.clear();
+        assert_eq!(world.validate(), vec![]);
+    }
+
+    #[test]
+    fn broken_bookkeeping_is_reported() {
+        let mut world = World::new();
+        let live = world.spawn().id();
+        let dead = world.spawn().id();
+        world.despawn(dead);
+        world.free_slots.borrow_mut().push(live.slot());
+        world.add_component(live, Children(vec![dead]));
+        world.add_component(live, Parent(live));
+        world.storage_or_insert::<Health>().insert(dead, Health, 0);
+        assert_eq!(
+            world.validate(),
+            vec![
+                ConsistencyError::DeadComponentOwner {
+                    component: std::any::type_name::<Health>(),
+                    id: dead,
+                },
+                ConsistencyError::LiveFreeSlot(live),
+                ConsistencyError::MissingChild {
+                    child: live,
+                    parent: live,
+                },
+                ConsistencyError::DanglingChild {
+                    parent: live,
+                    child: dead,
+                },
+            ]
+        );
+    }
+}
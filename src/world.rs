@@ -0,0 +1,1319 @@
+use crate::bundle::Bundle;
+use crate::command::Command;
+use crate::component::{assert_mutable, Component, ComponentSet};
+use crate::hierarchy::{Children, Parent};
+use crate::name::{Name, NameIndex};
+use crate::observer::Observers;
+use crate::query::{ComponentCombination, Filtered, Query, ReadOnlyComponentCombination};
+use crate::registry::ComponentRegistry;
+use crate::resource::{Res, ResMut};
+use crate::storage::{AnyStorage, ComponentStorage, StorageKind};
+use std::any::{type_name, Any, TypeId};
+use std::cell::{Cell, Ref, RefCell, RefMut};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// Opaque handle to an entity: a slot index plus the slot's generation,
+/// which changes every time the entity in the slot is despawned, so a stale
+/// handle never reaches whichever entity takes the slot next.
+///
+/// Displayed (and parsed) as `{index}v{generation}`, e.g. `3v0`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId {
+    index: u32,
+    generation: u32,
+}
+
+impl EntityId {
+    pub(crate) fn new(slot: usize, generation: u32) -> Self {
+        let index = u32::try_from(slot).expect("entity index overflowed u32");
+        Self { index, generation }
+    }
+    pub fn index(self) -> u32 {
+        self.index
+    }
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+    /// Packs the id into a `u64`, e.g. to send it over the network.
+    pub fn to_bits(self) -> u64 {
+        (u64::from(self.generation) << 32) | u64::from(self.index)
+    }
+    /// Inverse of `to_bits`.
+    pub fn from_bits(bits: u64) -> Self {
+        Self {
+            index: bits as u32,
+            generation: (bits >> 32) as u32,
+        }
+    }
+    pub(crate) fn slot(self) -> usize {
+        self.index as usize
+    }
+}
+
+impl fmt::Display for EntityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+impl fmt::Debug for EntityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseEntityIdError;
+
+impl fmt::Display for ParseEntityIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected an entity id like `3v0`")
+    }
+}
+
+impl std::error::Error for ParseEntityIdError {}
+
+/// Why an id doesn't refer to a live entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityError {
+    /// The entity was alive once and has been despawned since.
+    AlreadyDespawned(EntityId),
+    /// No entity ever had this id, e.g. one made up or from another world.
+    NeverExisted(EntityId),
+}
+
+impl fmt::Display for EntityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntityError::AlreadyDespawned(id) => write!(f, "entity {id} was already despawned"),
+            EntityError::NeverExisted(id) => write!(f, "entity {id} never existed"),
+        }
+    }
+}
+
+impl std::error::Error for EntityError {}
+
+impl FromStr for EntityId {
+    type Err = ParseEntityIdError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, generation) = s.split_once('v').ok_or(ParseEntityIdError)?;
+        Ok(Self {
+            index: index.parse().map_err(|_| ParseEntityIdError)?,
+            generation: generation.parse().map_err(|_| ParseEntityIdError)?,
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct EntityMeta {
+    alive: bool,
+    enabled: bool,
+    generation: u32,
+    /// The slot used up its last generation and is never handed out again,
+    /// since wrapping around would bring stale ids back to life.
+    retired: bool,
+    /// `despawn` is running its remove observers, which may try to despawn
+    /// the entity again.
+    despawning: bool,
+}
+
+impl EntityMeta {
+    /// The slot's state once the entity of `generation` is gone.
+    fn despawned(generation: u32) -> Self {
+        match generation.checked_add(1) {
+            Some(generation) => Self {
+                generation,
+                ..Self::default()
+            },
+            None => Self {
+                generation,
+                retired: true,
+                ..Self::default()
+            },
+        }
+    }
+}
+
+pub struct World {
+    first_index: usize,
+    pub(crate) next_index: Cell<usize>,
+    /// Slots below `next_index` whose entity was despawned, reused before
+    /// `next_index` grows.
+    pub(crate) free_slots: RefCell<Vec<usize>>,
+    pub(crate) entities: Vec<EntityMeta>,
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+    resources: HashMap<TypeId, RefCell<Box<dyn Any>>>,
+    change_tick: u64,
+    last_run_tick: Option<u64>,
+    pub(crate) structure_version: u64,
+    pub(crate) registry: ComponentRegistry,
+    pub(crate) observers: HashMap<TypeId, Observers>,
+    /// Components whose remove observers are running, so removing them
+    /// again from inside an observer doesn't notify twice.
+    pub(crate) removing: Vec<(TypeId, EntityId)>,
+    pub(crate) names: NameIndex,
+    pub(crate) command_queue: RefCell<Vec<Command>>,
+    #[cfg(feature = "serde")]
+    pub(crate) serializers: Vec<crate::serialize::ComponentSerializer>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            first_index: 0,
+            next_index: Cell::new(0),
+            free_slots: RefCell::new(Vec::new()),
+            entities: Vec::new(),
+            storages: HashMap::new(),
+            resources: HashMap::new(),
+            change_tick: 1,
+            last_run_tick: None,
+            structure_version: 0,
+            registry: ComponentRegistry::default(),
+            observers: HashMap::new(),
+            removing: Vec::new(),
+            names: NameIndex::default(),
+            command_queue: RefCell::new(Vec::new()),
+            #[cfg(feature = "serde")]
+            serializers: Vec::new(),
+        }
+    }
+    /// A world whose id indices count up from `seed` instead of 0. Ids are
+    /// handed out per world with no global state, so two worlds built with
+    /// the same seed and given the same spawns and despawns produce the same
+    /// id sequence. The seed is a starting index, not a hash: per-slot
+    /// bookkeeping and dense storages grow to cover every index below it,
+    /// so keep it small.
+    pub fn with_id_allocator(seed: u32) -> Self {
+        Self {
+            first_index: seed as usize,
+            next_index: Cell::new(seed as usize),
+            ..Self::new()
+        }
+    }
+    /// Allocates a live entity with no components yet, e.g. to fill in once
+    /// server data arrives.
+    pub fn reserve_entity(&mut self) -> EntityId {
+        let id = self.reserve_id();
+        self.register_reserved(id);
+        id
+    }
+    /// Hands out an unused id without making it alive, so it can be done
+    /// through `&self`; `register_reserved` makes it alive later. Slots of
+    /// despawned entities are reused, under their next generation, before
+    /// new ones are opened, so storages indexed by slot stay compact.
+    /// Panics once every index a `u32` can hold is in use or retired.
+    pub(crate) fn reserve_id(&self) -> EntityId {
+        let recycled = self.free_slots.borrow_mut().pop();
+        let slot = recycled.unwrap_or_else(|| {
+            let mut slot = self.next_index.get();
+            while self.entities.get(slot).is_some_and(|meta| meta.retired) {
+                slot += 1;
+            }
+            if slot > u32::MAX as usize {
+                panic!(
+                    "out of entity ids: all {} indices are taken",
+                    u64::from(u32::MAX) + 1
+                );
+            }
+            self.next_index.set(slot + 1);
+            slot
+        });
+        let generation = self.entities.get(slot).map_or(0, |meta| meta.generation);
+        EntityId::new(slot, generation)
+    }
+    /// Spawns one entity per bundle, inserting each component type into its
+    /// storage in one pass instead of entity by entity.
+    pub fn spawn_batch<B: Bundle, I: IntoIterator<Item = B>>(&mut self, batch: I) -> Vec<EntityId> {
+        let bundles: Vec<B> = batch.into_iter().collect();
+        self.entities.reserve(bundles.len());
+        B::reserve(self, bundles.len());
+        let ids: Vec<EntityId> = (0..bundles.len()).map(|_| self.reserve_entity()).collect();
+        B::insert_batch(self, &ids, bundles);
+        ids
+    }
+    /// Spawns an entity with `B::default()`, e.g.
+    /// `spawn_with_defaults::<(Collide, MoveTo)>()` for components whose
+    /// defaults are good enough.
+    pub fn spawn_with_defaults<B: Bundle + Default>(&mut self) -> EntityId {
+        let id = self.reserve_entity();
+        self.insert_bundle(id, B::default());
+        id
+    }
+    /// Spawns `bundle` on an entity that `App` despawns when the current
+    /// frame's systems are done. Spawned between frames (or by a command
+    /// applied at the end of one), it lives through the next frame.
+    pub fn spawn_temporary<B: Bundle>(&mut self, bundle: B) -> EntityId {
+        let id = self.reserve_entity();
+        self.insert_bundle(id, (bundle, Temporary));
+        id
+    }
+    pub(crate) fn despawn_temporaries(&mut self) {
+        let temporaries = self
+            .storage::<Temporary>()
+            .map_or_else(Vec::new, ComponentStorage::ids);
+        for id in temporaries {
+            self.despawn(id);
+        }
+    }
+    /// Spawns each bundle at its caller-provided id. Ids whose slot is
+    /// already taken (or repeated within the batch), and stale or retired
+    /// ids that `insert_or_spawn` would reject too, are skipped and returned
+    /// as the error; every other bundle is still spawned.
+    pub fn spawn_batch_with_ids<B: Bundle>(
+        &mut self,
+        batch: impl Iterator<Item = (EntityId, B)>,
+    ) -> Result<(), Vec<EntityId>> {
+        let mut conflicts = Vec::new();
+        for (id, bundle) in batch {
+            if !self.is_free_id(id) {
+                conflicts.push(id);
+                continue;
+            }
+            self.register_entity(id);
+            bundle.insert_into(self, id);
+        }
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(conflicts)
+        }
+    }
+    /// Inserts `bundle` on `id`, spawning the entity at exactly that id
+    /// (generation included) if its slot is free, e.g. for entities whose ids
+    /// a server assigns. The allocator won't hand the id out afterwards.
+    /// Hands `bundle` back if another generation of the slot is alive, or
+    /// if `id` is a despawned entity's or older, so stale handles stay dead.
+    pub fn insert_or_spawn<B: Bundle>(&mut self, id: EntityId, bundle: B) -> Result<(), B> {
+        if !self.is_alive(id) {
+            if !self.is_free_id(id) {
+                return Err(bundle);
+            }
+            self.register_entity(id);
+        }
+        bundle.insert_into(self, id);
+        Ok(())
+    }
+    /// Moves every entity of `other` into this world under a fresh id, e.g.
+    /// to load a prefab or sub-scene. `Parent` and `Children` are rewritten
+    /// to the new ids; other components holding ids are the caller's job,
+    /// using the returned map from old to new ids. `other`'s resources are
+    /// dropped.
+    pub fn extend(&mut self, mut other: World) -> HashMap<EntityId, EntityId> {
+        let old_ids: Vec<EntityId> = other.iter_entities().collect();
+        let ids: HashMap<EntityId, EntityId> = old_ids
+            .iter()
+            .map(|&old| (old, self.reserve_entity()))
+            .collect();
+        for &old in &old_ids {
+            if let Some(parent) = other.get_component_mut::<Parent>(old) {
+                parent.0 = ids.get(&parent.0).copied().unwrap_or(parent.0);
+            }
+            if let Some(children) = other.get_component_mut::<Children>(old) {
+                for child in &mut children.0 {
+                    *child = ids.get(child).copied().unwrap_or(*child);
+                }
+            }
+            if !other.is_enabled(old) {
+                self.set_enabled(ids[&old], false);
+            }
+        }
+        for (_, mut storage) in other.storages.drain() {
+            storage.move_into(self, &ids);
+        }
+        ids
+    }
+    pub fn is_alive(&self, id: EntityId) -> bool {
+        self.meta(id).is_some()
+    }
+    /// False for dead entities and ones switched off with `set_enabled`.
+    pub fn is_enabled(&self, id: EntityId) -> bool {
+        self.meta(id).is_some_and(|meta| meta.enabled)
+    }
+    /// The live entity's metadata; `None` for stale ids.
+    fn meta(&self, id: EntityId) -> Option<&EntityMeta> {
+        self.entities
+            .get(id.slot())
+            .filter(|meta| meta.alive && meta.generation == id.generation)
+    }
+    /// The id of the entity living in `slot`, if any.
+    pub(crate) fn entity_at(&self, slot: usize) -> Option<EntityId> {
+        let meta = self.entities.get(slot).filter(|meta| meta.alive)?;
+        Some(EntityId::new(slot, meta.generation))
+    }
+    pub(crate) fn is_queryable(&self, id: EntityId, include_disabled: bool) -> bool {
+        if include_disabled {
+            self.is_alive(id)
+        } else {
+            self.is_enabled(id)
+        }
+    }
+    /// Disabled entities keep their components but are skipped by queries
+    /// (see `query_including_disabled`), which makes them cheap to park and
+    /// reuse. Does nothing if `id` isn't alive.
+    pub fn set_enabled(&mut self, id: EntityId, enabled: bool) {
+        if !self.is_alive(id) {
+            return;
+        }
+        if let Some(meta) = self.entities.get_mut(id.slot()) {
+            if meta.enabled != enabled {
+                meta.enabled = enabled;
+                self.structure_version += 1;
+            }
+        }
+    }
+    /// Every live entity, components or not, in id order.
+    pub fn iter_entities(&self) -> impl Iterator<Item = EntityId> + '_ {
+        (0..self.entity_bound()).filter_map(|slot| self.entity_at(slot))
+    }
+    /// Whether there are no live entities; stops at the first one found.
+    pub fn is_empty(&self) -> bool {
+        !self.entities.iter().any(|meta| meta.alive)
+    }
+    /// Live entities with at least one of the set's component types, each
+    /// listed once, in id order.
+    pub fn entities_with_any<S: ComponentSet>(&self) -> Vec<EntityId> {
+        self.iter_entities()
+            .filter(|&id| S::any_present(self, id))
+            .collect()
+    }
+    /// Removes the entity and all of its components, detaching it from its
+    /// parent and children. Returns false if it wasn't alive, or is being
+    /// despawned already, e.g. by an observer of this very despawn.
+    pub fn despawn(&mut self, id: EntityId) -> bool {
+        match self.entities.get_mut(id.slot()) {
+            Some(meta) if meta.alive && meta.generation == id.generation && !meta.despawning => {
+                meta.despawning = true;
+            }
+            _ => return false,
+        }
+        self.remove_parent(id);
+        if let Some(Children(children)) = self.remove_component::<Children>(id) {
+            for child in children {
+                self.remove_component::<Parent>(child);
+            }
+        }
+        let observed: Vec<TypeId> = self
+            .observers
+            .iter()
+            .filter(|(type_id, observers)| {
+                observers.has_remove_observers()
+                    && self
+                        .storages
+                        .get(type_id)
+                        .is_some_and(|storage| storage.contains_entity(id))
+            })
+            .map(|(&type_id, _)| type_id)
+            .collect();
+        for type_id in observed {
+            self.trigger_remove(type_id, id);
+        }
+        for storage in self.storages.values_mut() {
+            storage.remove_entity(id);
+        }
+        self.names.remove(id);
+        let meta = EntityMeta::despawned(id.generation);
+        self.entities[id.slot()] = meta;
+        if !meta.retired {
+            self.free_slots.get_mut().push(id.slot());
+        }
+        self.structure_version += 1;
+        true
+    }
+    /// Like `despawn`, saying why when `id` isn't alive, to catch logic
+    /// errors like despawning the same entity twice. An entity whose despawn
+    /// is under way, e.g. one despawned again from its own remove observer,
+    /// counts as already despawned.
+    pub fn try_despawn(&mut self, id: EntityId) -> Result<(), EntityError> {
+        if self.despawn(id) {
+            return Ok(());
+        }
+        // Despawning bumps the slot's generation, so only generations below
+        // the current one were ever alive.
+        match self.entities.get(id.slot()) {
+            Some(meta)
+                if id.generation < meta.generation
+                    || meta.retired
+                    || (meta.despawning && id.generation == meta.generation) =>
+            {
+                Err(EntityError::AlreadyDespawned(id))
+            }
+            _ => Err(EntityError::NeverExisted(id)),
+        }
+    }
+    /// Despawns `id` and every entity below it in the hierarchy, descendants
+    /// before their ancestors. Entities reachable twice (a cycle from a
+    /// self-parenting bug) are only visited once. Returns how many entities
+    /// were removed.
+    pub fn despawn_recursive(&mut self, id: EntityId) -> usize {
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+        let mut stack = vec![id];
+        while let Some(next) = stack.pop() {
+            if !visited.insert(next) {
+                continue;
+            }
+            order.push(next);
+            if let Some(Children(children)) = self.get_component::<Children>(next) {
+                stack.extend(children.iter().rev());
+            }
+        }
+        order
+            .into_iter()
+            .rev()
+            .filter(|&entity| self.despawn(entity))
+            .count()
+    }
+    /// Despawns every live entity without a `T`, disabled ones included,
+    /// e.g. to sweep up entities that lost a component they can't do
+    /// without. Returns how many were removed.
+    pub fn despawn_where_missing<T: Component>(&mut self) -> usize {
+        let missing: Vec<EntityId> = self
+            .iter_entities()
+            .filter(|&id| !self.has_component::<T>(id))
+            .collect();
+        missing.into_iter().filter(|&id| self.despawn(id)).count()
+    }
+    /// Despawns everything and restarts id indices from the first one, with
+    /// new generations so old ids stay dead. Resources are kept; see
+    /// `clear_resources`.
+    pub fn clear(&mut self) {
+        for storage in self.storages.values_mut() {
+            storage.clear();
+        }
+        self.names.clear();
+        for meta in &mut self.entities {
+            if meta.alive {
+                *meta = EntityMeta::despawned(meta.generation);
+            }
+        }
+        self.next_index.set(self.first_index);
+        self.free_slots.get_mut().clear();
+        self.structure_version += 1;
+    }
+    /// Inserts `component`, replacing and returning the entity's previous
+    /// `T` if it had one. See `insert_if_absent` to keep the existing one.
+    /// Does nothing if `id` isn't alive.
+    pub fn add_component<T: Component>(&mut self, id: EntityId, component: T) -> Option<T> {
+        if !self.is_alive(id) {
+            return None;
+        }
+        let tick = self.change_tick;
+        let previous = self.storage_or_insert::<T>().insert(id, component, tick);
+        if previous.is_none() {
+            self.structure_version += 1;
+        }
+        self.trigger_insert(TypeId::of::<T>(), id);
+        previous
+    }
+    /// Inserts `component` only if the entity has no `T` yet; otherwise the
+    /// existing one is kept and `component` is handed back, as it is when
+    /// `id` isn't alive.
+    pub fn insert_if_absent<T: Component>(&mut self, id: EntityId, component: T) -> Result<(), T> {
+        if !self.is_alive(id) || self.has_component::<T>(id) {
+            return Err(component);
+        }
+        self.add_component(id, component);
+        Ok(())
+    }
+    pub fn remove_component<T: Component>(&mut self, id: EntityId) -> Option<T> {
+        if !self.has_component::<T>(id) {
+            return None;
+        }
+        self.trigger_remove(TypeId::of::<T>(), id);
+        let component = self.storage_mut::<T>()?.remove(id)?;
+        self.structure_version += 1;
+        Some(component)
+    }
+    /// Same as `remove_component`; reads better when the value is about to be
+    /// inserted on another entity.
+    pub fn take_component<T: Component>(&mut self, id: EntityId) -> Option<T> {
+        self.remove_component::<T>(id)
+    }
+    /// Moves `from`'s `T` onto `to`, replacing any `T` it had, e.g. to hand
+    /// a dead enemy's weapon to the player. Returns false, changing nothing,
+    /// if `from` has no `T` or `to` isn't alive.
+    pub fn transfer_component<T: Component>(&mut self, from: EntityId, to: EntityId) -> bool {
+        if from == to {
+            return self.has_component::<T>(from);
+        }
+        if !self.is_alive(to) {
+            return false;
+        }
+        match self.take_component::<T>(from) {
+            Some(component) => {
+                self.add_component(to, component);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Exchanges `a`'s and `b`'s `T`, moving it across when only one of them
+    /// has one. Returns false, changing nothing, if either isn't alive or
+    /// there was nothing to change.
+    pub fn swap_components<T: Component>(&mut self, a: EntityId, b: EntityId) -> bool {
+        if a == b || !self.is_alive(a) || !self.is_alive(b) {
+            return false;
+        }
+        match (self.take_component::<T>(a), self.take_component::<T>(b)) {
+            (None, None) => return false,
+            (from_a, from_b) => {
+                if let Some(component) = from_a {
+                    self.add_component(b, component);
+                }
+                if let Some(component) = from_b {
+                    self.add_component(a, component);
+                }
+            }
+        }
+        true
+    }
+    /// Picks how `T` is stored. Panics once any `T` has been inserted, since
+    /// existing components aren't migrated.
+    pub fn set_storage<T: Component>(&mut self, kind: StorageKind) {
+        if self
+            .storage::<T>()
+            .is_some_and(|storage| !storage.is_empty())
+        {
+            panic!(
+                "can't change the storage of `{}` after components were inserted",
+                type_name::<T>()
+            );
+        }
+        self.storages.insert(
+            TypeId::of::<T>(),
+            Box::new(ComponentStorage::<T>::with_kind(kind)),
+        );
+    }
+    /// Rough number of bytes held by component storages, spare capacity
+    /// included, for comparing layouts and spotting growth. Resources and
+    /// heap data owned by the components themselves aren't counted.
+    pub fn memory_usage(&self) -> usize {
+        self.storages
+            .values()
+            .map(|storage| storage.memory_usage())
+            .sum()
+    }
+    /// Grows `T`'s storage once up front so inserting `additional` more
+    /// components doesn't reallocate along the way.
+    pub fn reserve_components<T: Component>(&mut self, additional: usize) {
+        self.storage_or_insert::<T>().reserve(additional);
+    }
+    pub fn insert_bundle<B: Bundle>(&mut self, id: EntityId, bundle: B) {
+        bundle.insert_into(self, id);
+    }
+    /// Empties `T`'s storage, handing back every removed value with its
+    /// owner.
+    pub fn remove_all<T: Component>(&mut self) -> Vec<(EntityId, T)> {
+        if TypeId::of::<T>() == TypeId::of::<Name>() {
+            self.names.clear();
+        }
+        self.structure_version += 1;
+        self.storage_mut::<T>()
+            .map(ComponentStorage::remove_all)
+            .unwrap_or_default()
+    }
+    /// Like `remove_all`, handing the values out one at a time, e.g. to
+    /// harvest every pending message of the frame.
+    pub fn drain<T: Component>(&mut self) -> impl Iterator<Item = (EntityId, T)> {
+        self.remove_all::<T>().into_iter()
+    }
+    /// Replaces every `T`, disabled entities' included, with what `f` makes
+    /// of it, e.g. to double everyone's health. Each one counts as changed.
+    pub fn map_component<T: Component, F: FnMut(&T) -> T>(&mut self, mut f: F) {
+        let tick = self.change_tick;
+        let Some(storage) = self.storage_mut::<T>() else {
+            return;
+        };
+        for id in storage.ids() {
+            if let Some(component) = storage.get_mut(id) {
+                *component = f(component);
+            }
+            storage.mark_changed(id, tick);
+        }
+        if TypeId::of::<T>() == TypeId::of::<Name>() {
+            self.reindex_names();
+        }
+    }
+    /// Whether any entity, disabled or not, has a `T`; doesn't count them.
+    pub fn has_any<T: Component>(&self) -> bool {
+        self.storage::<T>()
+            .is_some_and(|storage| !storage.is_empty())
+    }
+    /// How many entities have a `T`, disabled ones included.
+    pub fn component_count<T: Component>(&self) -> usize {
+        self.storage::<T>().map_or(0, ComponentStorage::len)
+    }
+    /// A read-only query; see `query_mut` for ones with a `&mut` member.
+    /// Skips disabled entities. Matches come in ascending `EntityId` order
+    /// whatever the storage kind, so runs with the same operations iterate
+    /// identically, e.g. for replays or lockstep networking.
+    pub fn query<Q: ReadOnlyComponentCombination>(&self) -> Query<'_, Q> {
+        Query::new(self, false)
+    }
+    /// Like `query`, for any query including ones with a `&mut` member.
+    /// Borrowing the world mutably means no other query can overlap it.
+    pub fn query_mut<Q: ComponentCombination>(&mut self) -> Query<'_, Q> {
+        Query::new(self, false)
+    }
+    /// Like `query::<(D, F)>()`, yielding only `D`'s items, e.g.
+    /// `query_filtered::<(&MoveTo, &Collide), (With<Player>, Without<Frozen>)>()`.
+    pub fn query_filtered<D: ReadOnlyComponentCombination, F: ComponentCombination>(
+        &self,
+    ) -> Query<'_, Filtered<D, F>> {
+        Query::new(self, false)
+    }
+    /// Like `query_filtered`, for data with a `&mut` member.
+    pub fn query_filtered_mut<D: ComponentCombination, F: ComponentCombination>(
+        &mut self,
+    ) -> Query<'_, Filtered<D, F>> {
+        Query::new(self, false)
+    }
+    pub fn query_including_disabled<Q: ReadOnlyComponentCombination>(&self) -> Query<'_, Q> {
+        Query::new(self, true)
+    }
+    pub fn query_including_disabled_mut<Q: ComponentCombination>(&mut self) -> Query<'_, Q> {
+        Query::new(self, true)
+    }
+    pub fn has_component<T: Component>(&self, id: EntityId) -> bool {
+        self.storage::<T>()
+            .is_some_and(|storage| storage.contains(id))
+    }
+    /// Every component type the entity has, registered or not, sorted by
+    /// type name so two entities with the same types list them alike. Empty
+    /// for dead ids.
+    pub fn component_types_of(&self, id: EntityId) -> Vec<TypeId> {
+        self.components_of(id)
+            .into_iter()
+            .map(|(type_id, _)| type_id)
+            .collect()
+    }
+    pub fn get_component<T: Component>(&self, id: EntityId) -> Option<&T> {
+        self.storage::<T>()?.get(id)
+    }
+    /// Marks the component as changed, like a `&mut T` query would.
+    pub fn get_component_mut<T: Component>(&mut self, id: EntityId) -> Option<&mut T> {
+        const { assert_mutable::<T>() };
+        let tick = self.change_tick;
+        let storage = self.storage_mut::<T>()?;
+        storage.mark_changed(id, tick);
+        storage.get_mut(id)
+    }
+    /// The `T` of several distinct entities at once, e.g. to swap stats
+    /// between two of them. `None` if an id repeats or any entity lacks a
+    /// `T`. Each returned component is marked changed.
+    pub fn get_many_mut<T: Component, const N: usize>(
+        &mut self,
+        ids: [EntityId; N],
+    ) -> Option<[&mut T; N]> {
+        const { assert_mutable::<T>() };
+        if (1..N).any(|index| ids[..index].contains(&ids[index])) {
+            return None;
+        }
+        let tick = self.change_tick;
+        let storage = self.storage_mut::<T>()?;
+        if !ids.iter().all(|&id| storage.contains(id)) {
+            return None;
+        }
+        for id in ids {
+            storage.mark_changed(id, tick);
+        }
+        let storage = &*storage;
+        // SAFETY: the ids are distinct, so every reference points at a
+        // different component, and `&mut self` rules out any other.
+        Some(ids.map(|id| unsafe { storage.get_unchecked_mut(id) }.unwrap()))
+    }
+    /// The entity's `T`, inserting `default()` first if it has none. Either
+    /// way the component counts as changed. `None`, without calling
+    /// `default`, if `id` isn't alive.
+    pub fn get_or_insert_with<T: Component>(
+        &mut self,
+        id: EntityId,
+        default: impl FnOnce() -> T,
+    ) -> Option<&mut T> {
+        if !self.is_alive(id) {
+            return None;
+        }
+        if !self.has_component::<T>(id) {
+            self.add_component(id, default());
+        }
+        self.get_component_mut::<T>(id)
+    }
+    /// Components inserted or mutably fetched during the current tick count
+    /// as `Changed`.
+    pub fn change_tick(&self) -> u64 {
+        self.change_tick
+    }
+    /// `App` calls this once per schedule run, before applying commands.
+    /// Ticks are `u64`, so they don't wrap however long the app runs.
+    pub fn advance_tick(&mut self) {
+        self.change_tick += 1;
+    }
+    /// Set by `App` while a system runs to the tick that system last ran
+    /// at, so its `Changed` filters cover everything it hasn't seen yet.
+    pub(crate) fn set_last_run_tick(&mut self, tick: Option<u64>) {
+        self.last_run_tick = tick;
+    }
+    /// `Changed` matches components changed after this tick.
+    pub(crate) fn changed_since(&self) -> u64 {
+        self.last_run_tick.unwrap_or(self.change_tick - 1)
+    }
+    /// Bumped whenever an entity is spawned, despawned, enabled or disabled,
+    /// or gains or loses a component, so `QueryState` knows when its cached
+    /// matches are out of date.
+    pub(crate) fn structure_version(&self) -> u64 {
+        self.structure_version
+    }
+    /// One past the highest slot ever used.
+    pub(crate) fn entity_bound(&self) -> usize {
+        self.entities.len()
+    }
+    pub(crate) fn component_type_names(&self, id: EntityId) -> Vec<&'static str> {
+        self.components_of(id)
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect()
+    }
+    /// The entity's component types with their names, sorted by name.
+    pub(crate) fn components_of(&self, id: EntityId) -> Vec<(TypeId, &'static str)> {
+        let mut components: Vec<_> = self
+            .storages
+            .iter()
+            .filter(|(_, storage)| storage.contains_entity(id))
+            .map(|(&type_id, storage)| (type_id, storage.type_name()))
+            .collect();
+        components.sort_unstable_by_key(|&(_, name)| name);
+        components
+    }
+    pub(crate) fn storage_mut<T: Component>(&mut self) -> Option<&mut ComponentStorage<T>> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())?
+            .as_any_mut()
+            .downcast_mut::<ComponentStorage<T>>()
+    }
+    pub(crate) fn storage_or_insert<T: Component>(&mut self) -> &mut ComponentStorage<T> {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(ComponentStorage::<T>::new()))
+            .as_any_mut()
+            .downcast_mut::<ComponentStorage<T>>()
+            .unwrap()
+    }
+    /// Whether `id` can be brought to life with `register_entity`: its slot
+    /// is free, not retired, and `id` isn't a generation the slot already
+    /// went through, which would revive stale handles.
+    pub(crate) fn is_free_id(&self, id: EntityId) -> bool {
+        self.entities
+            .get(id.slot())
+            .is_none_or(|meta| !meta.alive && !meta.retired && id.generation >= meta.generation)
+    }
+    /// Makes `id` alive in a slot of the caller's choosing, e.g. a
+    /// server-assigned id, keeping the allocator from handing the slot out.
+    pub(crate) fn register_entity(&mut self, id: EntityId) {
+        let slot = id.slot();
+        let next_index = self.next_index.get();
+        if slot < next_index {
+            self.free_slots.get_mut().retain(|&free| free != slot);
+        } else {
+            let skipped = (next_index..slot)
+                .filter(|&free| !self.entities.get(free).is_some_and(|meta| meta.retired));
+            self.free_slots.get_mut().extend(skipped);
+            self.next_index.set(slot + 1);
+        }
+        self.register_reserved(id);
+    }
+    /// Makes alive an id handed out by `reserve_id`.
+    pub(crate) fn register_reserved(&mut self, id: EntityId) {
+        let slot = id.slot();
+        if self.entities.len() <= slot {
+            self.entities.resize(slot + 1, EntityMeta::default());
+        }
+        self.entities[slot] = EntityMeta {
+            alive: true,
+            enabled: true,
+            generation: id.generation,
+            ..EntityMeta::default()
+        };
+        self.structure_version += 1;
+    }
+    #[cfg(any(debug_assertions, test))]
+    pub(crate) fn iter_storages(&self) -> impl Iterator<Item = &dyn AnyStorage> + '_ {
+        self.storages.values().map(Box::as_ref)
+    }
+    pub(crate) fn storage_by_type_id(&self, type_id: TypeId) -> Option<&dyn AnyStorage> {
+        self.storages.get(&type_id).map(Box::as_ref)
+    }
+    pub(crate) fn storage<T: Component>(&self) -> Option<&ComponentStorage<T>> {
+        self.storages
+            .get(&TypeId::of::<T>())?
+            .as_any()
+            .downcast_ref::<ComponentStorage<T>>()
+    }
+
+    pub fn insert_resource<R: 'static>(&mut self, resource: R) {
+        self.resources
+            .insert(TypeId::of::<R>(), RefCell::new(Box::new(resource)));
+    }
+    pub fn remove_resource<R: 'static>(&mut self) -> Option<R> {
+        let resource = self.resources.remove(&TypeId::of::<R>())?;
+        resource
+            .into_inner()
+            .downcast::<R>()
+            .ok()
+            .map(|resource| *resource)
+    }
+    pub fn clear_resources(&mut self) {
+        self.resources.clear();
+    }
+    pub fn contains_resource<R: 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+    pub fn get_resource<R: 'static>(&self) -> Option<Res<'_, R>> {
+        let cell = self.resources.get(&TypeId::of::<R>())?;
+        Some(Res::new(Ref::map(cell.borrow(), |resource| {
+            resource.downcast_ref::<R>().unwrap()
+        })))
+    }
+    pub fn get_resource_mut<R: 'static>(&self) -> Option<ResMut<'_, R>> {
+        let cell = self.resources.get(&TypeId::of::<R>())?;
+        Some(ResMut::new(RefMut::map(cell.borrow_mut(), |resource| {
+            resource.downcast_mut::<R>().unwrap()
+        })))
+    }
+    /// Panics if the resource was never inserted.
+    pub fn resource<R: 'static>(&self) -> Res<'_, R> {
+        self.get_resource::<R>()
+            .unwrap_or_else(|| panic!("resource `{}` does not exist", type_name::<R>()))
+    }
+    /// Panics if the resource was never inserted.
+    pub fn resource_mut<R: 'static>(&self) -> ResMut<'_, R> {
+        self.get_resource_mut::<R>()
+            .unwrap_or_else(|| panic!("resource `{}` does not exist", type_name::<R>()))
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Marks entities from `spawn_temporary`.
+pub(crate) struct Temporary;
+impl Component for Temporary {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct A(u32);
+    impl Component for A {}
+
+    #[derive(Debug, PartialEq)]
+    struct B(u32);
+    impl Component for B {}
+
+    #[test]
+    fn remove_all_returns_every_value() {
+        let mut world = World::new();
+        let ids: Vec<EntityId> = (0..5)
+            .map(|value| world.spawn().insert(A(value)).id())
+            .collect();
+        let mut removed = world.remove_all::<A>();
+        removed.sort_by_key(|(id, _)| *id);
+        let expected: Vec<_> = ids
+            .iter()
+            .zip(0..)
+            .map(|(&id, value)| (id, A(value)))
+            .collect();
+        assert_eq!(removed, expected);
+        assert_eq!(world.component_count::<A>(), 0);
+        assert!(world.query::<&A>().iter().next().is_none());
+        assert!(ids.iter().all(|&id| world.is_alive(id)));
+    }
+
+    #[test]
+    fn insert_bundle_adds_every_component() {
+        let mut world = World::new();
+        let id = world.spawn().id();
+        world.insert_bundle(id, (A(1), B(2)));
+        assert_eq!(world.get_component::<A>(id), Some(&A(1)));
+        assert_eq!(world.get_component::<B>(id), Some(&B(2)));
+        let mut types = world.component_types_of(id);
+        types.sort();
+        let mut expected = vec![TypeId::of::<A>(), TypeId::of::<B>()];
+        expected.sort();
+        assert_eq!(types, expected);
+        assert_eq!(world.query::<(&A, &B)>().iter().count(), 1);
+    }
+
+    #[test]
+    fn get_or_insert_with_inserts_only_when_missing() {
+        let mut world = World::new();
+        let id = world.spawn().id();
+        world.get_or_insert_with(id, || A(1)).unwrap().0 += 1;
+        world.get_or_insert_with(id, || A(100)).unwrap().0 += 1;
+        assert_eq!(world.get_component::<A>(id), Some(&A(3)));
+    }
+
+    #[test]
+    fn get_or_insert_with_on_a_dead_entity_is_none() {
+        let mut world = World::new();
+        let id = world.spawn().id();
+        world.despawn(id);
+        assert!(world.get_or_insert_with(id, || A(1)).is_none());
+        assert!(!world.has_component::<A>(id));
+    }
+
+    #[test]
+    fn swap_components_with_both_present() {
+        let mut world = World::new();
+        let a = world.spawn().insert(A(1)).id();
+        let b = world.spawn().insert(A(2)).id();
+        assert!(world.swap_components::<A>(a, b));
+        assert_eq!(world.get_component::<A>(a), Some(&A(2)));
+        assert_eq!(world.get_component::<A>(b), Some(&A(1)));
+    }
+
+    #[test]
+    fn swap_components_with_only_first_present() {
+        let mut world = World::new();
+        let a = world.spawn().insert(A(1)).id();
+        let b = world.spawn().id();
+        assert!(world.swap_components::<A>(a, b));
+        assert_eq!(world.get_component::<A>(a), None);
+        assert_eq!(world.get_component::<A>(b), Some(&A(1)));
+    }
+
+    #[test]
+    fn swap_components_with_only_second_present() {
+        let mut world = World::new();
+        let a = world.spawn().id();
+        let b = world.spawn().insert(A(2)).id();
+        assert!(world.swap_components::<A>(a, b));
+        assert_eq!(world.get_component::<A>(a), Some(&A(2)));
+        assert_eq!(world.get_component::<A>(b), None);
+    }
+
+    #[test]
+    fn swap_components_with_neither_present() {
+        let mut world = World::new();
+        let a = world.spawn().id();
+        let b = world.spawn().id();
+        assert!(!world.swap_components::<A>(a, b));
+        assert!(!world.has_component::<A>(a) && !world.has_component::<A>(b));
+    }
+
+    #[test]
+    fn swap_components_with_a_dead_entity_keeps_the_component() {
+        let mut world = World::new();
+        let a = world.spawn().insert(A(1)).id();
+        let dead = world.spawn().id();
+        world.despawn(dead);
+        assert!(!world.swap_components::<A>(a, dead));
+        assert!(!world.swap_components::<A>(dead, a));
+        assert_eq!(world.get_component::<A>(a), Some(&A(1)));
+    }
+
+    #[test]
+    fn spawn_batch_with_ids_resolves_each_id() {
+        let mut world = World::new();
+        let ids = [
+            EntityId::new(5, 0),
+            EntityId::new(2, 3),
+            EntityId::new(9, 1),
+        ];
+        let batch = ids.iter().zip(0..).map(|(&id, value)| (id, (A(value),)));
+        assert_eq!(world.spawn_batch_with_ids(batch), Ok(()));
+        for (&id, value) in ids.iter().zip(0..) {
+            assert_eq!(world.get_component::<A>(id), Some(&A(value)));
+        }
+        let fresh: Vec<_> = (0..8).map(|_| world.spawn().id()).collect();
+        assert!(fresh.iter().all(|id| !ids.contains(id)));
+        assert_eq!(world.validate(), vec![]);
+    }
+
+    #[test]
+    fn spawn_batch_with_ids_reports_taken_stale_and_retired_ids() {
+        let mut world = World::new();
+        let taken = world.spawn().id();
+        let stale = world.spawn().id();
+        world.despawn(stale);
+        let retired = EntityId::new(7, u32::MAX);
+        world.insert_or_spawn(retired, (A(0),)).unwrap();
+        world.despawn(retired);
+        let fine = EntityId::new(3, 0);
+        let batch = [taken, stale, retired, fine, fine]
+            .into_iter()
+            .map(|id| (id, (A(1),)));
+        assert_eq!(
+            world.spawn_batch_with_ids(batch),
+            Err(vec![taken, stale, retired, fine])
+        );
+        assert!(!world.is_alive(stale) && !world.is_alive(retired));
+        assert_eq!(world.get_component::<A>(fine), Some(&A(1)));
+    }
+
+    #[test]
+    fn insert_or_spawn_rejects_stale_ids() {
+        let mut world = World::new();
+        let old = world.spawn().insert(A(1)).id();
+        world.despawn(old);
+        let newer = world.spawn().id();
+        world.despawn(newer);
+        assert_eq!(world.insert_or_spawn(old, (A(2),)), Err((A(2),)));
+        assert!(!world.is_alive(old));
+        assert!(world.insert_or_spawn(newer, (A(3),)).is_err());
+        let next = EntityId::new(old.slot(), newer.generation() + 1);
+        assert!(world.insert_or_spawn(next, (A(4),)).is_ok());
+        assert_eq!(world.get_component::<A>(next), Some(&A(4)));
+        assert_eq!(world.validate(), vec![]);
+    }
+
+    #[test]
+    fn insert_or_spawn_rejects_retired_slots() {
+        let mut world = World::new();
+        let last = EntityId::new(0, u32::MAX);
+        assert!(world.insert_or_spawn(last, (A(1),)).is_ok());
+        world.despawn(last);
+        assert!(world.insert_or_spawn(last, (A(2),)).is_err());
+        assert!(!world.is_alive(last));
+    }
+
+    #[test]
+    fn add_component_returns_the_replaced_value() {
+        let mut world = World::new();
+        let id = world.spawn().id();
+        assert_eq!(world.add_component(id, A(1)), None);
+        assert_eq!(world.add_component(id, A(2)), Some(A(1)));
+        assert_eq!(world.get_component::<A>(id), Some(&A(2)));
+    }
+
+    #[test]
+    fn insert_if_absent_keeps_the_existing_value() {
+        let mut world = World::new();
+        let id = world.spawn().id();
+        assert_eq!(world.insert_if_absent(id, A(1)), Ok(()));
+        assert_eq!(world.insert_if_absent(id, A(2)), Err(A(2)));
+        assert_eq!(world.get_component::<A>(id), Some(&A(1)));
+        world.despawn(id);
+        assert_eq!(world.insert_if_absent(id, A(3)), Err(A(3)));
+    }
+
+    #[test]
+    fn get_many_mut_hands_out_distinct_components() {
+        let mut world = World::new();
+        let first = world.spawn().insert(A(1)).id();
+        let second = world.spawn().insert(A(2)).id();
+        let [a, b] = world.get_many_mut::<A, 2>([first, second]).unwrap();
+        std::mem::swap(a, b);
+        assert_eq!(world.get_component::<A>(first), Some(&A(2)));
+        assert_eq!(world.get_component::<A>(second), Some(&A(1)));
+    }
+
+    #[test]
+    fn get_many_mut_rejects_repeated_and_missing_ids() {
+        let mut world = World::new();
+        let first = world.spawn().insert(A(1)).id();
+        let bare = world.spawn().id();
+        assert!(world.get_many_mut::<A, 2>([first, first]).is_none());
+        assert!(world.get_many_mut::<A, 2>([first, bare]).is_none());
+        world.despawn(first);
+        assert!(world.get_many_mut::<A, 1>([first]).is_none());
+    }
+
+    #[test]
+    fn spawn_despawn_churn_reuses_slots() {
+        let mut world = World::new();
+        let churn = |world: &mut World| {
+            for value in 0..1000 {
+                let id = world.spawn().insert(A(value)).id();
+                world.despawn(id);
+            }
+        };
+        churn(&mut world);
+        let memory = world.memory_usage();
+        churn(&mut world);
+        assert_eq!(world.entity_bound(), 1);
+        assert_eq!(world.spawn().id().generation(), 2000);
+        assert_eq!(world.memory_usage(), memory);
+    }
+
+    #[test]
+    fn last_index_is_handed_out_before_running_out() {
+        let world = World::with_id_allocator(u32::MAX - 1);
+        assert_eq!(world.reserve_id().index(), u32::MAX - 1);
+        assert_eq!(world.reserve_id().index(), u32::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of entity ids")]
+    fn running_out_of_ids_panics() {
+        let world = World::with_id_allocator(u32::MAX);
+        world.reserve_id();
+        world.reserve_id();
+    }
+
+    #[test]
+    fn is_empty_and_has_any_follow_spawns() {
+        let mut world = World::new();
+        assert!(world.is_empty());
+        assert!(!world.has_any::<A>());
+        let id = world.spawn().id();
+        assert!(!world.is_empty());
+        assert!(!world.has_any::<A>());
+        world.add_component(id, A(1));
+        world.set_enabled(id, false);
+        assert!(world.has_any::<A>());
+        assert!(!world.has_any::<B>());
+        world.despawn(id);
+        assert!(world.is_empty());
+        assert!(!world.has_any::<A>());
+    }
+
+    #[test]
+    fn retired_slots_stay_retired_after_clear() {
+        let mut world = World::new();
+        let last = EntityId::new(0, u32::MAX);
+        assert!(world.insert_or_spawn(last, (A(1),)).is_ok());
+        world.despawn(last);
+        world.clear();
+        assert!(world.insert_or_spawn(EntityId::new(5, 0), (A(2),)).is_ok());
+        let spawned: Vec<EntityId> = (0..6).map(|_| world.spawn().id()).collect();
+        assert!(spawned.iter().all(|id| id.slot() != 0));
+        assert!(!world.is_alive(last));
+        assert_eq!(world.validate(), vec![]);
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_ids() {
+        fn ids(seed: u32) -> Vec<EntityId> {
+            let mut world = World::with_id_allocator(seed);
+            let mut ids: Vec<EntityId> = (0..4).map(|_| world.spawn().id()).collect();
+            world.despawn(ids[1]);
+            world.despawn(ids[2]);
+            ids.extend((0..3).map(|_| world.spawn().id()));
+            ids
+        }
+        let first = ids(100);
+        assert_eq!(first, ids(100));
+        assert_eq!(first[0], EntityId::new(100, 0));
+        assert_ne!(first, ids(0));
+    }
+
+    #[test]
+    fn try_despawn_tells_stale_from_unknown_ids() {
+        let mut world = World::new();
+        let id = world.spawn().id();
+        assert_eq!(world.try_despawn(id), Ok(()));
+        assert_eq!(
+            world.try_despawn(id),
+            Err(EntityError::AlreadyDespawned(id))
+        );
+        let unknown = EntityId::new(7, 0);
+        assert_eq!(
+            world.try_despawn(unknown),
+            Err(EntityError::NeverExisted(unknown))
+        );
+    }
+
+    #[test]
+    fn try_despawn_during_despawn_reports_already_despawned() {
+        let mut world = World::new();
+        let result = std::rc::Rc::new(std::cell::Cell::new(None));
+        let seen = result.clone();
+        world.observe_remove::<A>(move |id, world| seen.set(Some(world.try_despawn(id))));
+        let id = world.spawn().insert(A(1)).id();
+        assert!(world.despawn(id));
+        assert_eq!(result.get(), Some(Err(EntityError::AlreadyDespawned(id))));
+    }
+
+    #[test]
+    fn extend_remaps_the_hierarchy() {
+        let mut world = World::new();
+        let existing = world.spawn().insert(A(0)).id();
+        let mut prefab = World::new();
+        let root = prefab.spawn().insert(A(1)).id();
+        let child = prefab.spawn().insert(A(2)).id();
+        prefab.set_parent(child, root);
+
+        let ids = world.extend(prefab);
+        assert_eq!(ids.len(), 2);
+        let (new_root, new_child) = (ids[&root], ids[&child]);
+        assert!(new_root != existing && new_child != existing);
+        assert_eq!(world.get_component::<A>(new_root), Some(&A(1)));
+        assert_eq!(world.get_component::<A>(new_child), Some(&A(2)));
+        assert_eq!(
+            world.get_component::<Parent>(new_child),
+            Some(&Parent(new_root))
+        );
+        assert_eq!(
+            world.get_component::<Children>(new_root),
+            Some(&Children(vec![new_child]))
+        );
+        assert_eq!(world.get_component::<A>(existing), Some(&A(0)));
+        assert_eq!(world.validate(), vec![]);
+    }
+
+    #[test]
+    fn transfer_component_moves_one_way() {
+        let mut world = World::new();
+        let enemy = world.spawn().insert(A(3)).id();
+        let player = world.spawn().insert(A(1)).id();
+        assert!(world.transfer_component::<A>(enemy, player));
+        assert_eq!(world.get_component::<A>(player), Some(&A(3)));
+        assert!(!world.has_component::<A>(enemy));
+        assert!(!world.transfer_component::<A>(enemy, player));
+    }
+
+    #[test]
+    fn transfer_component_from_a_dead_entity_does_nothing() {
+        let mut world = World::new();
+        let dead = world.spawn().insert(A(3)).id();
+        let player = world.spawn().insert(A(1)).id();
+        world.despawn(dead);
+        assert!(!world.transfer_component::<A>(dead, player));
+        assert_eq!(world.get_component::<A>(player), Some(&A(1)));
+        let living = world.spawn().insert(A(5)).id();
+        assert!(!world.transfer_component::<A>(living, dead));
+        assert_eq!(world.get_component::<A>(living), Some(&A(5)));
+    }
+
+    #[test]
+    fn despawn_where_missing_sweeps_only_entities_without_the_component() {
+        let mut world = World::new();
+        assert_eq!(world.despawn_where_missing::<A>(), 0);
+        let kept = world.spawn().insert(A(1)).id();
+        let orphan = world.spawn().insert(B(2)).id();
+        let bare = world.spawn().id();
+        assert_eq!(world.despawn_where_missing::<A>(), 2);
+        assert!(world.is_alive(kept));
+        assert!(!world.is_alive(orphan) && !world.is_alive(bare));
+        assert_eq!(world.get_component::<A>(kept), Some(&A(1)));
+        assert_eq!(world.despawn_where_missing::<B>(), 1);
+        assert!(world.is_empty());
+        assert_eq!(world.despawn_where_missing::<B>(), 0);
+    }
+
+    #[test]
+    fn set_storage_before_any_insert_keeps_components_reachable() {
+        let mut world = World::new();
+        world.set_storage::<A>(StorageKind::Map);
+        let id = world.spawn().insert(A(4)).id();
+        world.get_component_mut::<A>(id).unwrap().0 += 1;
+        assert_eq!(world.get_component::<A>(id), Some(&A(5)));
+        world.remove_component::<A>(id);
+        world.set_storage::<A>(StorageKind::SparseSet);
+    }
+
+    #[test]
+    #[should_panic(expected = "can't change the storage of")]
+    fn set_storage_panics_once_instances_exist() {
+        let mut world = World::new();
+        world.spawn().insert(A(1));
+        world.set_storage::<A>(StorageKind::Map);
+    }
+}